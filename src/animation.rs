@@ -0,0 +1,257 @@
+use crate::fonts::FontEnvironment;
+use crate::read_rgba_pixels;
+use crate::render_diff::{compare, RenderComparison};
+use crate::save_rgba_png;
+use crate::styled_text::StyledText;
+use crate::text_on_path::{paragraph_glyphs, span_paint, Glyph};
+use skia_safe::{Color, ISize, Point, Surface, TextBlob, Vector};
+
+// What an animation addresses: single glyphs, words or lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Glyph,
+    Word,
+    Line,
+}
+
+// The animated properties of a unit in one frame. The default leaves it as laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Properties {
+    pub opacity: f32,
+    pub offset: Vector,
+    // Of each glyph, around the centre of its advance on the baseline.
+    pub scale: f32,
+    // Extra advance after each glyph, moving the rest of the line. Lines aren't broken again, so
+    // this can push text past the layout width.
+    pub letter_spacing: f32,
+}
+
+impl Default for Properties {
+    fn default() -> Self {
+        Properties {
+            opacity: 1.0,
+            offset: Vector::new(0.0, 0.0),
+            scale: 1.0,
+            letter_spacing: 0.0,
+        }
+    }
+}
+
+pub struct Animation {
+    pub unit: Unit,
+    pub frame_count: usize,
+    // The properties of unit `index` of `count` at time `t`, which runs from 0 on the first frame
+    // to 1 on the last.
+    pub properties: Box<dyn Fn(usize, usize, f32) -> Properties>,
+}
+
+impl Animation {
+    // Glyphs appear one after another.
+    pub fn typewriter(frame_count: usize) -> Self {
+        Animation {
+            unit: Unit::Glyph,
+            frame_count,
+            properties: Box::new(|index, count, t| Properties {
+                opacity: if (index as f32) < t * count as f32 {
+                    1.0
+                } else {
+                    0.0
+                },
+                ..Properties::default()
+            }),
+        }
+    }
+
+    // Words fade in one after another, each overlapping the next.
+    pub fn fade_in_by_word(frame_count: usize) -> Self {
+        Animation {
+            unit: Unit::Word,
+            frame_count,
+            properties: Box::new(|index, count, t| Properties {
+                opacity: staggered(index, count, t),
+                ..Properties::default()
+            }),
+        }
+    }
+
+    // Lines slide up by `distance` and fade in one after another.
+    pub fn slide_up(frame_count: usize, distance: f32) -> Self {
+        Animation {
+            unit: Unit::Line,
+            frame_count,
+            properties: Box::new(move |index, count, t| {
+                let progress = staggered(index, count, t);
+                let eased = 1.0 - (1.0 - progress).powi(3);
+                Properties {
+                    opacity: progress,
+                    offset: Vector::new(0.0, distance * (1.0 - eased)),
+                    ..Properties::default()
+                }
+            }),
+        }
+    }
+
+    // Glyphs grow from nothing while the letter spacing closes up.
+    pub fn zoom_in(frame_count: usize, spacing: f32) -> Self {
+        Animation {
+            unit: Unit::Glyph,
+            frame_count,
+            properties: Box::new(move |_, _, t| Properties {
+                opacity: t,
+                scale: t,
+                letter_spacing: spacing * (1.0 - t),
+                ..Properties::default()
+            }),
+        }
+    }
+}
+
+// Progress of unit `index` when the units start one after another and each takes twice the gap
+// between their starts.
+fn staggered(index: usize, count: usize, t: f32) -> f32 {
+    let duration = 2.0 / (count as f32 + 1.0);
+    let start = index as f32 * duration / 2.0;
+    ((t - start) / duration).max(0.0).min(1.0)
+}
+
+pub struct FrameReport {
+    pub frames_written: usize,
+    pub glyph_count: usize,
+    pub unit_count: usize,
+    pub clusters_missing: usize,
+    // The last frame against the paragraph painted directly. With an animation that ends at
+    // rest, differences come from glyphs the per-glyph rendering places or maps differently.
+    pub final_frame: RenderComparison,
+    // Frames in which a glyph moved although its own properties didn't change, i.e. it was
+    // pushed by the letter spacing of glyphs before it.
+    pub drifting_frames: Vec<usize>,
+}
+
+// Renders `animation` over `text` into `{prefix}_0000.png`, `{prefix}_0001.png`, ... The png
+// crate this project uses can't write APNG (the animation chunks came with png 0.17), so frames
+// are numbered images for now.
+//
+// The paragraph is laid out once and every frame is drawn from its glyph positions, so changing
+// properties can't reflow the text. An animation without frames is an error, as there's no final
+// frame to compare.
+pub fn render_frames(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    width: f32,
+    size: ISize,
+    animation: &Animation,
+    prefix: &str,
+) -> Result<FrameReport, String> {
+    if animation.frame_count == 0 {
+        return Err("the animation has no frames".to_string());
+    }
+    let paragraph = text.layout(fonts, width);
+    let (glyphs, clusters_missing) = paragraph_glyphs(text, &paragraph, fonts);
+    let units = units(&glyphs, &text.text(), animation.unit);
+    let unit_count = units.iter().max().map(|unit| unit + 1).unwrap_or(0);
+
+    let mut frames_written = 0;
+    let mut drifting_frames = vec![];
+    let mut last_frame = vec![];
+    let mut previous: Option<(Vec<Properties>, Vec<Point>)> = None;
+    for frame in 0..animation.frame_count {
+        let t = if animation.frame_count > 1 {
+            frame as f32 / (animation.frame_count - 1) as f32
+        } else {
+            1.0
+        };
+        let properties: Vec<Properties> = (0..unit_count)
+            .map(|unit| (animation.properties)(unit, unit_count, t))
+            .collect();
+        let mut surface = Surface::new_raster_n32_premul(size).unwrap();
+        let canvas = surface.canvas();
+        canvas.clear(Color::WHITE);
+
+        let mut line = usize::MAX;
+        let mut spacing = 0.0;
+        let mut origins = Vec::with_capacity(glyphs.len());
+        for (glyph, unit) in glyphs.iter().zip(units.iter()) {
+            if glyph.line != line {
+                line = glyph.line;
+                spacing = 0.0;
+            }
+            let unit_properties = &properties[*unit];
+            let origin = Point::new(glyph.x + spacing, glyph.baseline + glyph.y_offset)
+                + unit_properties.offset;
+            spacing += unit_properties.letter_spacing;
+            origins.push(origin);
+            if unit_properties.opacity <= 0.0 {
+                continue;
+            }
+            let blob = match TextBlob::from_text(&[glyph.id][..], &glyph.font) {
+                Some(blob) => blob,
+                None => continue,
+            };
+            let mut paint = span_paint(text, glyph.run.0);
+            paint.set_alpha_f(paint.alpha_f() * unit_properties.opacity.min(1.0));
+            canvas.save();
+            let centre = Point::new(origin.x + glyph.advance / 2.0, origin.y);
+            canvas.translate(centre);
+            canvas.scale((unit_properties.scale, unit_properties.scale));
+            canvas.translate(-centre);
+            canvas.draw_text_blob(&blob, origin, &paint);
+            canvas.restore();
+        }
+        if let Some((previous_properties, previous_origins)) = &previous {
+            let drifted = units
+                .iter()
+                .zip(origins.iter().zip(previous_origins.iter()))
+                .any(|(unit, (origin, previous_origin))| {
+                    properties[*unit] == previous_properties[*unit] && origin != previous_origin
+                });
+            if drifted {
+                drifting_frames.push(frame);
+            }
+        }
+        previous = Some((properties, origins));
+
+        let pixels = match read_rgba_pixels(&mut surface) {
+            Some(pixels) => pixels,
+            None => continue,
+        };
+        let path = format!("{}_{:04}.png", prefix, frame);
+        if save_rgba_png(&pixels, size.width, size.height, &path) {
+            frames_written += 1;
+        }
+        last_frame = pixels;
+    }
+
+    let mut reference = Surface::new_raster_n32_premul(size).unwrap();
+    reference.canvas().clear(Color::WHITE);
+    paragraph.paint(reference.canvas(), Point::new(0.0, 0.0));
+    let reference = read_rgba_pixels(&mut reference).unwrap_or_default();
+    let (final_frame, _) = compare(&reference, &last_frame);
+    Ok(FrameReport {
+        frames_written,
+        glyph_count: glyphs.len(),
+        unit_count,
+        clusters_missing,
+        final_frame,
+        drifting_frames,
+    })
+}
+
+// The unit index of every glyph. A word runs up to and including the whitespace after it.
+fn units(glyphs: &[Glyph], text: &str, unit: Unit) -> Vec<usize> {
+    let mut units = Vec::with_capacity(glyphs.len());
+    let mut word = 0;
+    let mut after_space = false;
+    for (index, glyph) in glyphs.iter().enumerate() {
+        let is_space = text[glyph.cluster.clone()].chars().all(char::is_whitespace);
+        if after_space && !is_space {
+            word += 1;
+        }
+        after_space = is_space;
+        units.push(match unit {
+            Unit::Glyph => index,
+            Unit::Word => word,
+            Unit::Line => glyph.line,
+        });
+    }
+    units
+}
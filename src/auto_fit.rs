@@ -0,0 +1,163 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use crate::text_index::utf8_to_utf16;
+use crate::text_on_path::layout_clusters;
+use skia_safe::textlayout::{Paragraph, RectHeightStyle, RectWidthStyle};
+
+// Layout results are compared against the box with a small tolerance: SkParagraph sums glyph
+// advances in f32, so text that is exactly as wide as the box can come out a hair wider.
+const EPSILON: f32 = 0.001;
+
+pub struct FitOptions {
+    pub min_font_size: f32,
+    pub max_font_size: f32,
+    pub max_lines: Option<usize>,
+    // Sizes closer than this are considered equal by the search.
+    pub precision: f32,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        FitOptions {
+            min_font_size: 1.0,
+            max_font_size: 1000.0,
+            max_lines: None,
+            precision: 0.1,
+        }
+    }
+}
+
+pub struct FitResult {
+    pub font_size: f32,
+    // False when even `min_font_size` overflows the box. `font_size` is the minimum then.
+    pub fits: bool,
+    pub height: f32,
+    pub line_count: usize,
+}
+
+// Finds the largest font size at which `text` fits into a `width` x `height` box.
+//
+// The fit isn't monotonic in the font size: a smaller size can move a line break so that a word
+// no longer fits, and at some widths a glyph is dropped from the line altogether (see
+// `disappearing_letter()`). So the bisection result is only a starting point: sizes just above it
+// are probed for a larger fit and sizes below it are re-checked until one actually fits.
+pub fn fit_font_size(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    width: f32,
+    height: f32,
+    options: &FitOptions,
+) -> FitResult {
+    let check = |font_size: f32| -> Option<FitResult> {
+        let resized = text.with_font_size(font_size);
+        let paragraph = resized.layout(fonts, width);
+        if fits(&paragraph, width, height, options.max_lines)
+            && all_clusters_laid_out(&resized, fonts, &paragraph)
+        {
+            Some(FitResult {
+                font_size,
+                fits: true,
+                height: paragraph.height(),
+                line_count: paragraph.line_number(),
+            })
+        } else {
+            None
+        }
+    };
+
+    if let Some(result) = check(options.max_font_size) {
+        return result;
+    }
+
+    let mut low = options.min_font_size;
+    let mut high = options.max_font_size;
+    let mut best = check(low);
+    if best.is_some() {
+        while high - low > options.precision {
+            let middle = (low + high) / 2.0;
+            match check(middle) {
+                Some(result) => {
+                    low = middle;
+                    best = Some(result);
+                }
+                None => high = middle,
+            }
+        }
+
+        // Look for a larger fit past the point where the bisection stopped.
+        let probe_steps = 10;
+        for step in 1..=probe_steps {
+            let font_size = low + options.precision * step as f32;
+            if font_size >= options.max_font_size {
+                break;
+            }
+            if let Some(result) = check(font_size) {
+                best = Some(result);
+            }
+        }
+    } else {
+        // The minimum size doesn't fit, but a slightly larger one might if the minimum happens to
+        // hit a bad line break.
+        let mut font_size = low + options.precision;
+        while best.is_none() && font_size <= (low + options.precision * 10.0).min(high) {
+            best = check(font_size);
+            font_size += options.precision;
+        }
+    }
+
+    best.unwrap_or_else(|| {
+        let paragraph = text
+            .with_font_size(options.min_font_size)
+            .layout(fonts, width);
+        FitResult {
+            font_size: options.min_font_size,
+            fits: false,
+            height: paragraph.height(),
+            line_count: paragraph.line_number(),
+        }
+    })
+}
+
+fn fits(paragraph: &Paragraph, width: f32, height: f32, max_lines: Option<usize>) -> bool {
+    if paragraph.height() > height + EPSILON {
+        return false;
+    }
+    // A word wider than the box gets broken up by SkParagraph instead of overflowing, which is
+    // never what a shrinking text box wants.
+    if paragraph.min_intrinsic_width() > width + EPSILON {
+        return false;
+    }
+    if paragraph.longest_line() > width + EPSILON {
+        return false;
+    }
+    match max_lines {
+        Some(max_lines) => paragraph.get_line_metrics().len() <= max_lines,
+        None => true,
+    }
+}
+
+// Whether every visible cluster of `text` has a box in `paragraph`. At some widths SkParagraph
+// drops a glyph instead of wrapping it, while the line metrics still cover its text, so the
+// clusters of a single-line layout are looked up one by one.
+fn all_clusters_laid_out(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    paragraph: &Paragraph,
+) -> bool {
+    let text_string = text.text();
+    let single_line = text.layout(fonts, 1_000_000_f32);
+    layout_clusters(&single_line, &text_string, 0, &text_string)
+        .into_iter()
+        .filter(|(range, _)| !text_string[range.clone()].chars().all(char::is_whitespace))
+        .all(|(range, _)| {
+            paragraph
+                .get_rects_for_range(
+                    utf8_to_utf16(&text_string, range.start)
+                        ..utf8_to_utf16(&text_string, range.end),
+                    RectHeightStyle::Tight,
+                    RectWidthStyle::Tight,
+                )
+                .iter()
+                .any(|text_box| text_box.rect.width() > 0.0)
+        })
+}
@@ -0,0 +1,147 @@
+use crate::fonts::FontEnvironment;
+use crate::read_rgba_pixels;
+use crate::render_diff::{has_colour, has_ink};
+use crate::sfnt::{table, u16_at};
+use crate::styled_text::StyledText;
+use skia_safe::textlayout::{ParagraphStyle, TextStyle};
+use skia_safe::{Canvas, Color, Font, ISize, Paint, Surface, Typeface};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColourFormat {
+    ColrV0,
+    ColrV1,
+    CbdtCblc,
+    Sbix,
+    Svg,
+}
+
+// Every colour technology a font carries, in the order FreeType prefers them.
+pub fn colour_formats(typeface: &Typeface) -> Vec<ColourFormat> {
+    let mut formats = vec![];
+    if let Some(colr) = table(typeface, b"COLR") {
+        if u16_at(&colr, 0).unwrap_or(0) >= 1 {
+            formats.push(ColourFormat::ColrV1);
+        }
+        // A v1 table can carry v0 base glyphs too.
+        if u16_at(&colr, 2).unwrap_or(0) > 0 {
+            formats.push(ColourFormat::ColrV0);
+        }
+    }
+    if table(typeface, b"CBDT").is_some() && table(typeface, b"CBLC").is_some() {
+        formats.push(ColourFormat::CbdtCblc);
+    }
+    if table(typeface, b"sbix").is_some() {
+        formats.push(ColourFormat::Sbix);
+    }
+    if table(typeface, b"SVG ").is_some() {
+        formats.push(ColourFormat::Svg);
+    }
+    formats
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlyphOutcome {
+    Colour,
+    // The font has a glyph, but only its monochrome outline was drawn.
+    MonochromeFallback,
+    // The font has a glyph, but nothing was drawn.
+    Blank,
+    // The font has no glyph for at least one character of the sample.
+    Missing,
+}
+
+pub struct SampleResult {
+    pub sample: &'static str,
+    pub font_size: f32,
+    pub outcome: GlyphOutcome,
+}
+
+pub const SAMPLES: &[&str] = &[
+    "😀",
+    "❤\u{FE0F}",
+    "👍🏽",
+    "🇬🇧",
+    "1\u{FE0F}\u{20E3}",
+    "❤\u{FE0F}\u{200D}🔥",
+    "🧔\u{200D}♀\u{FE0F}",
+    "🏳\u{FE0F}\u{200D}🌈",
+];
+
+pub const SIZES: &[f32] = &[16.0, 32.0, 64.0, 128.0];
+
+// Renders every sample at every size with `family` as the only font (no fallback), draws the
+// results into `canvas` as one row per size, and classifies each of them.
+pub fn render_matrix(
+    fonts: &FontEnvironment,
+    family: &str,
+    canvas: &mut Canvas,
+    top: f32,
+) -> Vec<SampleResult> {
+    let typeface = fonts.typeface(family).cloned();
+    let mut results = vec![];
+    let mut y = top;
+    for font_size in SIZES.iter() {
+        let cell = font_size * 1.5;
+        for (column, sample) in SAMPLES.iter().enumerate() {
+            let mut text_style = TextStyle::new();
+            let mut paint = Paint::default();
+            paint.set_color(Color::from_rgb(0, 0, 0));
+            text_style.set_foreground_color(paint);
+            text_style.set_font_size(*font_size);
+            text_style.set_font_families(&vec![family]);
+            let mut style = ParagraphStyle::new();
+            style.set_text_style(&text_style);
+            let mut text = StyledText::new(style);
+            text.add_span(*sample, text_style);
+            let paragraph = text.layout(fonts, cell * 4.0);
+
+            let size = ISize::new((cell * 4.0) as i32, cell as i32);
+            let mut surface = Surface::new_raster_n32_premul(size).unwrap();
+            surface.canvas().clear(Color::from_rgb(255, 255, 255));
+            paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+            let pixels = read_rgba_pixels(&mut surface).unwrap_or_default();
+            let outcome = if !covers(typeface.as_ref(), sample) {
+                GlyphOutcome::Missing
+            } else if !has_ink(&pixels) {
+                GlyphOutcome::Blank
+            } else if !has_colour(&pixels) {
+                GlyphOutcome::MonochromeFallback
+            } else {
+                GlyphOutcome::Colour
+            };
+            results.push(SampleResult {
+                sample: *sample,
+                font_size: *font_size,
+                outcome,
+            });
+            paragraph.paint(canvas, skia_safe::Point::new(column as f32 * cell * 1.2, y));
+        }
+        y += cell;
+    }
+    results
+}
+
+pub fn matrix_height() -> f32 {
+    SIZES.iter().map(|font_size| font_size * 1.5).sum()
+}
+
+pub fn matrix_width() -> f32 {
+    SIZES.iter().cloned().fold(0.0, f32::max) * 1.5 * 1.2 * SAMPLES.len() as f32
+}
+
+// Whether the font maps every character of the sample except the invisible joiners and
+// selectors, which are often left to the shaper.
+fn covers(typeface: Option<&Typeface>, sample: &str) -> bool {
+    let typeface = match typeface {
+        Some(typeface) => typeface.clone(),
+        None => return false,
+    };
+    let characters: String = sample
+        .chars()
+        .filter(|c| *c != '\u{200D}' && *c != '\u{FE0F}')
+        .collect();
+    let font = Font::from_typeface(typeface, Some(16.0));
+    let mut glyph_ids = vec![0; characters.chars().count()];
+    font.str_to_glyphs(&characters, &mut glyph_ids);
+    !glyph_ids.iter().any(|glyph| *glyph == 0)
+}
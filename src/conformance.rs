@@ -0,0 +1,361 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use crate::text_index::utf8_to_utf16;
+use crate::text_on_path::layout_clusters;
+use skia_safe::textlayout::{ParagraphStyle, TextStyle};
+use skia_safe::{Color, Point};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+// A line of one of the Unicode break test files (LineBreakTest.txt, GraphemeBreakTest.txt,
+// WordBreakTest.txt), e.g. `× 0023 × 0020 ÷ 0023 ÷ #  × [0.3] NUMBER SIGN (AL) ...`.
+pub struct TestCase {
+    pub line_number: usize,
+    pub text: String,
+    // Whether there is a boundary before each character and at the end.
+    pub boundaries: Vec<bool>,
+    // The rule deciding each boundary, from the comment, e.g. "7.01".
+    pub rules: Vec<String>,
+}
+
+impl TestCase {
+    // Character index to UTF-8 offset, one more entry than there are characters.
+    pub fn utf8_offsets(&self) -> Vec<usize> {
+        self.text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(self.text.len()))
+            .collect()
+    }
+
+    // The case in the test file's notation, with `×` and `÷` marking `boundaries`.
+    pub fn describe(&self, boundaries: &[bool]) -> String {
+        let mut description = String::new();
+        for (index, character) in self.text.chars().enumerate() {
+            description.push_str(if boundaries[index] { "÷ " } else { "× " });
+            description.push_str(&format!("{:04X} ", character as u32));
+        }
+        description.push_str(if boundaries[boundaries.len() - 1] {
+            "÷"
+        } else {
+            "×"
+        });
+        description
+    }
+}
+
+pub fn read_test_file(file_path: &str) -> io::Result<Vec<TestCase>> {
+    let contents = fs::read_to_string(file_path)?;
+    let mut cases = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let mut parts = line.splitn(2, '#');
+        let data = parts.next().unwrap_or("").trim();
+        let comment = parts.next().unwrap_or("");
+        if data.is_empty() {
+            continue;
+        }
+        let mut text = String::new();
+        let mut boundaries = vec![];
+        for token in data.split_whitespace() {
+            match token {
+                "÷" => boundaries.push(true),
+                "×" => boundaries.push(false),
+                code => match u32::from_str_radix(code, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                {
+                    Some(character) => text.push(character),
+                    // Lone surrogates can't be put in a Rust string.
+                    None => break,
+                },
+            }
+        }
+        if boundaries.len() != text.chars().count() + 1 {
+            continue;
+        }
+        let rules: Vec<String> = comment
+            .split('[')
+            .skip(1)
+            .filter_map(|part| part.split(']').next())
+            .map(str::to_string)
+            .collect();
+        let rules = if rules.len() == boundaries.len() {
+            rules
+        } else {
+            vec![String::new(); boundaries.len()]
+        };
+        cases.push(TestCase {
+            line_number: index + 1,
+            text,
+            boundaries,
+            rules,
+        });
+    }
+    Ok(cases)
+}
+
+#[derive(Default)]
+pub struct RuleMismatches {
+    // Breaks the standard has that the layout didn't take.
+    pub missing: usize,
+    // Breaks the layout took that the standard doesn't allow.
+    pub unexpected: usize,
+    // Unexpected breaks after a line holding a single cluster. SkParagraph breaks words that
+    // don't fit the width anywhere, and at the tiny width used here every cluster is too wide,
+    // so these are most likely that fallback rather than ICU's rules.
+    pub emergency: usize,
+    pub first_failure: Option<String>,
+}
+
+pub struct ConformanceReport {
+    pub cases: usize,
+    pub passed: usize,
+    // Cases whose only differences are emergency breaks.
+    pub passed_except_emergency: usize,
+    pub rules: BTreeMap<String, RuleMismatches>,
+}
+
+impl ConformanceReport {
+    fn new() -> Self {
+        ConformanceReport {
+            cases: 0,
+            passed: 0,
+            passed_except_emergency: 0,
+            rules: BTreeMap::new(),
+        }
+    }
+
+    // Records the boundaries of `case` that differ from `actual`. The start and end of the text
+    // are always boundaries and aren't compared. `emergency` marks actual boundaries that are
+    // likely emergency breaks.
+    fn compare(&mut self, case: &TestCase, actual: &[bool], emergency: &[bool]) {
+        self.cases += 1;
+        let mut failed = false;
+        let mut only_emergency = true;
+        for index in 1..case.boundaries.len() - 1 {
+            let expected = case.boundaries[index];
+            if actual[index] == expected {
+                continue;
+            }
+            let emergency = actual[index] && emergency[index];
+            failed = true;
+            only_emergency &= emergency;
+            let mismatches = self.rules.entry(case.rules[index].clone()).or_default();
+            if expected {
+                mismatches.missing += 1;
+            } else if emergency {
+                mismatches.emergency += 1;
+            } else {
+                mismatches.unexpected += 1;
+            }
+            if mismatches.first_failure.is_none() && !emergency {
+                mismatches.first_failure = Some(format!(
+                    "line {}: expected {}, got {}",
+                    case.line_number,
+                    case.describe(&case.boundaries),
+                    case.describe(&actual_with_ends(actual))
+                ));
+            }
+        }
+        if !failed {
+            self.passed += 1;
+        } else if only_emergency {
+            self.passed_except_emergency += 1;
+        }
+    }
+
+    pub fn print(&self, title: &str) {
+        print!("{}: {} of {} cases pass", title, self.passed, self.cases);
+        if self.passed_except_emergency > 0 {
+            print!(
+                ", {} more differ only by emergency breaks",
+                self.passed_except_emergency
+            );
+        }
+        println!();
+        for (rule, mismatches) in self.rules.iter() {
+            print!(
+                "  rule {:>6}: {} missing, {} unexpected",
+                rule, mismatches.missing, mismatches.unexpected
+            );
+            if mismatches.emergency > 0 {
+                print!(", {} emergency", mismatches.emergency);
+            }
+            println!();
+            if let Some(failure) = &mismatches.first_failure {
+                println!("    e.g. {}", failure);
+            }
+        }
+    }
+}
+
+// Lays every case out in `family` at a width of one pixel, so every break opportunity ICU finds
+// becomes a line break, and compares the line ends from get_line_metrics() with the test's
+// boundaries. The start and end of the text are always boundaries and aren't compared.
+pub fn check_line_breaks(
+    cases: &[TestCase],
+    fonts: &FontEnvironment,
+    family: &str,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::new();
+    for case in cases.iter() {
+        let paragraph = single_style_text(case, family).layout(fonts, 1.0);
+        let offsets = case.utf8_offsets();
+        let clusters = layout_clusters(&paragraph, &case.text, 0, &case.text);
+
+        let mut actual = vec![false; case.boundaries.len()];
+        let mut single_cluster_line = vec![false; case.boundaries.len()];
+        let lines = paragraph.get_line_metrics();
+        for line in lines.iter().take(lines.len().saturating_sub(1)) {
+            if let Ok(index) = offsets.binary_search(&line.end_including_newline) {
+                actual[index] = true;
+                single_cluster_line[index] = clusters
+                    .iter()
+                    .filter(|(range, _)| {
+                        range.start >= line.start_index && range.end <= line.end_including_newline
+                    })
+                    .count()
+                    <= 1;
+            }
+        }
+
+        report.compare(case, &actual, &single_cluster_line);
+    }
+    report
+}
+
+// Lays every case out on a single line and takes the edges of get_word_boundary() at every
+// offset as the word boundaries.
+pub fn check_word_boundaries(
+    cases: &[TestCase],
+    fonts: &FontEnvironment,
+    family: &str,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::new();
+    for case in cases.iter() {
+        let paragraph = single_style_text(case, family).layout(fonts, 1_000_000_f32);
+        let offsets = utf16_offsets(case);
+        let mut actual = vec![false; case.boundaries.len()];
+        for offset in offsets.iter() {
+            let word = paragraph.get_word_boundary(*offset as u32);
+            for edge in [word.start, word.end].iter() {
+                if let Ok(index) = offsets.binary_search(edge) {
+                    actual[index] = true;
+                }
+            }
+        }
+        report.compare(case, &actual, &vec![false; actual.len()]);
+    }
+    report
+}
+
+// Lays every case out and hit tests along each line every quarter pixel. The caret positions
+// get_glyph_position_at_coordinate() returns are the cluster boundaries a user can move the
+// caret to. Clusters without width, like most controls, can't be hit and show up as missing
+// boundaries.
+pub fn check_grapheme_boundaries(
+    cases: &[TestCase],
+    fonts: &FontEnvironment,
+    family: &str,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::new();
+    for case in cases.iter() {
+        let paragraph = single_style_text(case, family).layout(fonts, 1_000_000_f32);
+        let offsets = utf16_offsets(case);
+        let mut actual = vec![false; case.boundaries.len()];
+        let width = paragraph.max_intrinsic_width();
+        for line in paragraph.get_line_metrics().iter() {
+            let mut x = -1.0;
+            while x <= width + 1.0 {
+                let position =
+                    paragraph.get_glyph_position_at_coordinate(Point::new(x, line.baseline as f32));
+                if let Ok(index) = offsets.binary_search(&(position.position.max(0) as usize)) {
+                    actual[index] = true;
+                }
+                x += 0.25;
+            }
+        }
+        report.compare(case, &actual, &vec![false; actual.len()]);
+    }
+    report
+}
+
+fn single_style_text(case: &TestCase, family: &str) -> StyledText {
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::BLACK);
+    text_style.set_font_size(16.0);
+    text_style.set_font_families(&[family]);
+    let mut paragraph_style = ParagraphStyle::new();
+    paragraph_style.set_text_style(&text_style);
+    let mut text = StyledText::new(paragraph_style);
+    text.add_span(case.text.clone(), text_style);
+    text
+}
+
+// UTF-16 offset of every character of a case and of its end, for the paragraph APIs that use
+// them.
+fn utf16_offsets(case: &TestCase) -> Vec<usize> {
+    case.utf8_offsets()
+        .into_iter()
+        .map(|offset| utf8_to_utf16(&case.text, offset))
+        .collect()
+}
+
+fn actual_with_ends(actual: &[bool]) -> Vec<bool> {
+    let mut actual = actual.to_vec();
+    actual[0] = true;
+    let last = actual.len() - 1;
+    actual[last] = true;
+    actual
+}
+
+// `line-break-test <LineBreakTest.txt> [font file]`
+pub fn run_line_break_test(args: &[String]) {
+    if let Some((cases, fonts)) = load(args, "line-break-test <LineBreakTest.txt> [font file]") {
+        check_line_breaks(&cases, &fonts, "Conformance").print("UAX #14 line breaking");
+    }
+}
+
+// `grapheme-break-test <GraphemeBreakTest.txt> [font file]`
+pub fn run_grapheme_break_test(args: &[String]) {
+    let usage = "grapheme-break-test <GraphemeBreakTest.txt> [font file]";
+    if let Some((cases, fonts)) = load(args, usage) {
+        check_grapheme_boundaries(&cases, &fonts, "Conformance")
+            .print("UAX #29 grapheme clusters (hit testing)");
+    }
+}
+
+// `word-break-test <WordBreakTest.txt> [font file]`
+pub fn run_word_break_test(args: &[String]) {
+    if let Some((cases, fonts)) = load(args, "word-break-test <WordBreakTest.txt> [font file]") {
+        check_word_boundaries(&cases, &fonts, "Conformance")
+            .print("UAX #29 word boundaries (get_word_boundary)");
+    }
+}
+
+// Reads the test file named in `args` and registers the font file after it, or OpenSans, as
+// "Conformance".
+fn load(args: &[String], usage: &str) -> Option<(Vec<TestCase>, FontEnvironment)> {
+    let test_path = match args.first() {
+        Some(test_path) => test_path,
+        None => {
+            println!("usage: {}", usage);
+            return None;
+        }
+    };
+    let cases = match read_test_file(test_path) {
+        Ok(cases) => cases,
+        Err(error) => {
+            println!("can't read {}: {}", test_path, error);
+            return None;
+        }
+    };
+    let font_path = args
+        .get(1)
+        .map(String::as_str)
+        .unwrap_or("OpenSans-Regular.ttf");
+    let mut fonts = FontEnvironment::new().with_system_fallback();
+    fonts.register(font_path, "Conformance");
+    Some((cases, fonts))
+}
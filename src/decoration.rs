@@ -0,0 +1,80 @@
+use crate::sfnt::{i16_at, table, u16_at};
+use skia_safe::textlayout::{TextDecoration, TextDecorationMode, TextDecorationStyle, TextStyle};
+use skia_safe::{Color, Font, Typeface};
+
+pub struct Decoration {
+    // Any combination of UNDERLINE, OVERLINE and LINE_THROUGH.
+    pub lines: TextDecoration,
+    pub style: TextDecorationStyle,
+    pub color: Color,
+    // Multiplies the thickness the font asks for in post.underlineThickness and
+    // OS/2.yStrikeoutSize.
+    pub thickness_multiplier: f32,
+    // Whether underlines break around descenders.
+    pub skip_ink: bool,
+}
+
+impl Default for Decoration {
+    fn default() -> Self {
+        Decoration {
+            lines: TextDecoration::UNDERLINE,
+            style: TextDecorationStyle::Solid,
+            color: Color::from_rgb(0, 0, 0),
+            thickness_multiplier: 1.0,
+            skip_ink: true,
+        }
+    }
+}
+
+impl Decoration {
+    pub fn apply(&self, style: &mut TextStyle) {
+        style.set_decoration_type(self.lines);
+        style.set_decoration_style(self.style);
+        style.set_decoration_color(self.color);
+        style.set_decoration_thickness_multiplier(self.thickness_multiplier);
+        style.set_decoration_mode(if self.skip_ink {
+            TextDecorationMode::Gaps
+        } else {
+            TextDecorationMode::Through
+        });
+    }
+}
+
+// Decoration positions and thicknesses at a font size, from the font tables and as skia reads
+// them. Positions are relative to the baseline, positive is below it.
+pub struct DecorationMetrics {
+    pub post_underline_position: Option<f32>,
+    pub post_underline_thickness: Option<f32>,
+    pub os2_strikeout_position: Option<f32>,
+    pub os2_strikeout_thickness: Option<f32>,
+    pub skia_underline_position: Option<f32>,
+    pub skia_underline_thickness: Option<f32>,
+    pub skia_strikeout_position: Option<f32>,
+    pub skia_strikeout_thickness: Option<f32>,
+}
+
+impl DecorationMetrics {
+    pub fn read(typeface: &Typeface, font_size: f32) -> DecorationMetrics {
+        let units_per_em = table(typeface, b"head")
+            .and_then(|head| u16_at(&head, 18))
+            .unwrap_or(1000);
+        let scale = font_size / units_per_em as f32;
+        let post = table(typeface, b"post").unwrap_or_default();
+        let os2 = table(typeface, b"OS/2").unwrap_or_default();
+        // The tables measure upwards from the baseline, skia measures downwards.
+        let scaled = |value: Option<i16>, sign: f32| value.map(|value| value as f32 * scale * sign);
+
+        let font = Font::from_typeface(typeface.clone(), Some(font_size));
+        let (_, metrics) = font.metrics();
+        DecorationMetrics {
+            post_underline_position: scaled(i16_at(&post, 8), -1.0),
+            post_underline_thickness: scaled(i16_at(&post, 10), 1.0),
+            os2_strikeout_position: scaled(i16_at(&os2, 28), -1.0),
+            os2_strikeout_thickness: scaled(i16_at(&os2, 26), 1.0),
+            skia_underline_position: metrics.underline_position(),
+            skia_underline_thickness: metrics.underline_thickness(),
+            skia_strikeout_position: metrics.strikeout_position(),
+            skia_strikeout_thickness: metrics.strikeout_thickness(),
+        }
+    }
+}
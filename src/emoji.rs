@@ -0,0 +1,355 @@
+use crate::emoji_data::{in_ranges, EMOJI, EMOJI_PRESENTATION};
+use crate::fonts::FontEnvironment;
+use crate::inspect::colour_tables;
+use crate::read_rgba_pixels;
+use crate::render_diff::has_colour;
+use crate::styled_text::{Span, StyledText};
+use skia_safe::textlayout::{ParagraphStyle, TextStyle};
+use skia_safe::{Color, Font, ISize, Paint, Surface};
+use std::ops::Range;
+
+pub const VS15: char = '\u{FE0E}';
+pub const VS16: char = '\u{FE0F}';
+const ZWJ: char = '\u{200D}';
+const KEYCAP: char = '\u{20E3}';
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Presentation {
+    // Follow the variation selectors in the text and Unicode's Emoji_Presentation property.
+    Default,
+    Emoji,
+    Text,
+}
+
+pub struct Cluster {
+    pub range: Range<usize>,
+    // Whether the cluster can be presented as an emoji at all. Runs of other text are grouped
+    // into a single cluster.
+    pub is_emoji: bool,
+    // The presentation the text asks for; always `Text` for non-emoji clusters.
+    pub presentation: Presentation,
+}
+
+// Splits text into emoji sequences (UTS #51: modifiers, keycaps, flags, tags and ZWJ sequences)
+// and the runs of text between them.
+pub fn clusters(text: &str) -> Vec<Cluster> {
+    let characters: Vec<(usize, char)> = text.char_indices().collect();
+    let mut clusters: Vec<Cluster> = vec![];
+    let mut index = 0;
+    while index < characters.len() {
+        let (start, character) = characters[index];
+        // Digits, # and * are emoji only as the base of a keycap or with VS16; otherwise they are
+        // ordinary text and stay in its run.
+        let keycap_base = is_keycap_base(character)
+            && characters
+                .get(index + 1)
+                .map(|(_, next)| *next != VS16 && *next != KEYCAP)
+                .unwrap_or(true);
+        if !is_emoji(character) || keycap_base {
+            index += 1;
+            let end = characters
+                .get(index)
+                .map(|(offset, _)| *offset)
+                .unwrap_or(text.len());
+            match clusters.last_mut() {
+                Some(last) if !last.is_emoji => last.range.end = end,
+                _ => clusters.push(Cluster {
+                    range: start..end,
+                    is_emoji: false,
+                    presentation: Presentation::Text,
+                }),
+            }
+            continue;
+        }
+
+        let mut sequence = false;
+        index += 1;
+        if is_regional_indicator(character) {
+            if let Some((_, next)) = characters.get(index) {
+                if is_regional_indicator(*next) {
+                    sequence = true;
+                    index += 1;
+                }
+            }
+        }
+        while let Some((_, next)) = characters.get(index) {
+            let next = *next;
+            if next == VS15 || next == VS16 {
+                index += 1;
+            } else if next == KEYCAP || is_modifier(next) || is_tag(next) {
+                sequence = true;
+                index += 1;
+            } else if next == ZWJ
+                && characters
+                    .get(index + 1)
+                    .map(|(_, joined)| is_emoji(*joined))
+                    .unwrap_or(false)
+            {
+                sequence = true;
+                index += 2;
+            } else {
+                break;
+            }
+        }
+        let end = characters
+            .get(index)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(text.len());
+        let cluster_text = &text[start..end];
+        let presentation = if cluster_text.contains(VS15) && !sequence {
+            Presentation::Text
+        } else if sequence
+            || cluster_text.contains(VS16)
+            || in_ranges(EMOJI_PRESENTATION, character as u32)
+        {
+            Presentation::Emoji
+        } else {
+            Presentation::Text
+        };
+        clusters.push(Cluster {
+            range: start..end,
+            is_emoji: true,
+            presentation,
+        });
+    }
+    clusters
+}
+
+// Rewrites an emoji cluster so that it asks for `presentation` with a variation selector.
+// Sequences (ZWJ, keycaps, flags, modifiers) only exist as emoji, so they are only ever forced
+// towards emoji presentation.
+pub fn with_presentation(cluster: &str, presentation: Presentation) -> String {
+    let mut characters = cluster.chars();
+    let base = match characters.next() {
+        Some(base) => base,
+        None => return String::new(),
+    };
+    // Only the selector right after the base is replaced. Fully-qualified sequences like 🏳️‍🌈
+    // carry more of them, which the font's ligatures need.
+    let rest = characters.as_str();
+    let rest = rest
+        .strip_prefix(|c| c == VS15 || c == VS16)
+        .unwrap_or(rest);
+    let is_sequence = !rest.is_empty();
+    match presentation {
+        Presentation::Default => cluster.to_string(),
+        Presentation::Emoji => format!("{}{}{}", base, VS16, rest),
+        Presentation::Text if is_sequence => cluster.to_string(),
+        Presentation::Text => format!("{}{}", base, VS15),
+    }
+}
+
+// Rewrites every emoji cluster of `text` with `with_presentation()` and leaves the text between
+// them as it is.
+pub fn apply_presentation(text: &str, presentation: Presentation) -> String {
+    if presentation == Presentation::Default {
+        return text.to_string();
+    }
+    clusters(text)
+        .into_iter()
+        .map(|cluster| {
+            let cluster_text = &text[cluster.range];
+            if cluster.is_emoji {
+                with_presentation(cluster_text, presentation)
+            } else {
+                cluster_text.to_string()
+            }
+        })
+        .collect()
+}
+
+// Splits the spans of `text` at the clusters presented as emoji and sends them to
+// `emoji_family` by appending it to their family list. Everything else stays in one span per
+// original span, with `emoji_family` removed from its list, so a text-presentation ❤︎ isn't taken
+// from the emoji font just because it's listed, and shaping runs aren't broken up. The text itself
+// is left alone, so indices into the paragraph match `text.text()`; spans with a forced
+// presentation already got the matching variation selectors from
+// `StyledText::add_span_with_presentation()`.
+pub fn route_emoji(text: &StyledText, emoji_family: &str) -> StyledText {
+    let mut routed = StyledText::new(text.paragraph_style.clone());
+    for span in text.spans.iter() {
+        let text_span = |part: &str| Span {
+            text: part.to_string(),
+            style: with_families(&span.style, emoji_family, false),
+            presentation: span.presentation,
+            effect: span.effect.clone(),
+            placeholder: span.placeholder.clone(),
+        };
+        let mut text_start = 0;
+        for cluster in clusters(&span.text) {
+            let cluster_text = &span.text[cluster.range.clone()];
+            if !cluster.is_emoji || resolve(&cluster, cluster_text, span) != Presentation::Emoji {
+                continue;
+            }
+            if text_start < cluster.range.start {
+                routed
+                    .spans
+                    .push(text_span(&span.text[text_start..cluster.range.start]));
+            }
+            routed.spans.push(Span {
+                text: cluster_text.to_string(),
+                style: with_families(&span.style, emoji_family, true),
+                presentation: Presentation::Emoji,
+                effect: span.effect.clone(),
+                placeholder: None,
+            });
+            text_start = cluster.range.end;
+        }
+        if text_start < span.text.len() {
+            routed.spans.push(text_span(&span.text[text_start..]));
+        }
+    }
+    routed
+}
+
+// The presentation an emoji cluster of `span` ends up with: the span's forced presentation if it
+// has one, except for sequences that can't be shown as text.
+fn resolve(cluster: &Cluster, cluster_text: &str, span: &Span) -> Presentation {
+    let presentation = match span.presentation {
+        Presentation::Default => cluster.presentation,
+        forced => forced,
+    };
+    if presentation == Presentation::Text
+        && clusters(cluster_text)[0].presentation == Presentation::Emoji
+    {
+        Presentation::Emoji
+    } else {
+        presentation
+    }
+}
+
+fn with_families(style: &TextStyle, emoji_family: &str, emoji: bool) -> TextStyle {
+    let mut families: Vec<String> = style
+        .font_families()
+        .iter()
+        .filter(|family| *family != emoji_family)
+        .map(|family| family.to_string())
+        .collect();
+    if emoji {
+        families.push(emoji_family.to_string());
+    }
+    let mut style = style.clone();
+    style.set_font_families(&families);
+    style
+}
+
+pub struct ClusterReport {
+    pub text: String,
+    pub requested: Presentation,
+    pub resolved: Presentation,
+    // The first family in the cluster's list whose font has glyphs for the whole cluster, or
+    // None if it is left to system fallback.
+    pub font_family: Option<String>,
+    pub colour_font: bool,
+    pub rendered_in_colour: bool,
+}
+
+// Reports, per emoji cluster, which presentation was asked for, which one routing resolved to,
+// which registered font covers it and whether it actually came out in colour.
+pub fn presentation_report(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    emoji_family: &str,
+) -> Vec<ClusterReport> {
+    let mut reports = vec![];
+    let emoji_clusters = text.spans.iter().flat_map(|span| {
+        clusters(&span.text)
+            .into_iter()
+            .filter(|cluster| cluster.is_emoji)
+            .map(move |cluster| (cluster, span))
+    });
+    for (cluster, original) in emoji_clusters {
+        let cluster_text = &original.text[cluster.range.clone()];
+        let requested = match original.presentation {
+            Presentation::Default => cluster.presentation,
+            forced => forced,
+        };
+        let resolved = resolve(&cluster, cluster_text, original);
+        let span = Span {
+            text: cluster_text.to_string(),
+            style: with_families(
+                &original.style,
+                emoji_family,
+                resolved == Presentation::Emoji,
+            ),
+            presentation: resolved,
+            effect: None,
+            placeholder: None,
+        };
+        let covering_characters: String = span
+            .text
+            .chars()
+            .filter(|c| *c != VS15 && *c != VS16 && *c != ZWJ && !is_tag(*c))
+            .collect();
+        let font_family = span
+            .style
+            .font_families()
+            .iter()
+            .find(|family| {
+                fonts
+                    .typeface(family)
+                    .map(|typeface| {
+                        let font = Font::from_typeface(typeface.clone(), Some(16.0));
+                        let mut glyph_ids = vec![0; covering_characters.chars().count()];
+                        font.str_to_glyphs(&covering_characters, &mut glyph_ids);
+                        !glyph_ids.iter().any(|glyph| *glyph == 0)
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|family| family.to_string());
+        let colour_font = font_family
+            .as_ref()
+            .and_then(|family| fonts.typeface(family))
+            .map(|typeface| !colour_tables(typeface).is_empty())
+            .unwrap_or(false);
+        reports.push(ClusterReport {
+            text: span.text.clone(),
+            requested,
+            resolved,
+            font_family,
+            colour_font,
+            rendered_in_colour: renders_in_colour(&span, fonts),
+        });
+    }
+    reports
+}
+
+fn renders_in_colour(span: &Span, fonts: &FontEnvironment) -> bool {
+    let mut style = span.style.clone();
+    let mut paint = Paint::default();
+    paint.set_color(Color::from_rgb(0, 0, 0));
+    style.set_foreground_color(paint);
+    style.set_font_size(32.0);
+    let mut paragraph_style = ParagraphStyle::new();
+    paragraph_style.set_text_style(&style);
+    let mut text = StyledText::new(paragraph_style);
+    text.add_span(span.text.clone(), style);
+    let paragraph = text.layout(fonts, 200.0);
+
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(200, 64)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+    read_rgba_pixels(&mut surface)
+        .map(|pixels| has_colour(&pixels))
+        .unwrap_or(false)
+}
+
+fn is_emoji(character: char) -> bool {
+    in_ranges(EMOJI, character as u32)
+}
+
+fn is_keycap_base(character: char) -> bool {
+    character.is_ascii_digit() || character == '#' || character == '*'
+}
+
+fn is_regional_indicator(character: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&character)
+}
+
+fn is_modifier(character: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&character)
+}
+
+fn is_tag(character: char) -> bool {
+    ('\u{E0020}'..='\u{E007F}').contains(&character)
+}
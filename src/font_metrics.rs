@@ -0,0 +1,155 @@
+use crate::fonts::FontEnvironment;
+use crate::sfnt::{i16_at, table, u16_at};
+use crate::styled_text::StyledText;
+use skia_safe::textlayout::{ParagraphStyle, TextStyle};
+use skia_safe::{Font, Typeface};
+
+// fsSelection bit 7: use the typo metrics rather than the win metrics for line spacing.
+const USE_TYPO_METRICS: u16 = 1 << 7;
+
+// Vertical metrics in font units, straight from the font tables. Descenders are negative, as
+// stored in hhea and OS/2, except for usWinDescent which is stored as a positive number.
+pub struct TableMetrics {
+    pub units_per_em: u16,
+    pub hhea_ascender: Option<i16>,
+    pub hhea_descender: Option<i16>,
+    pub hhea_line_gap: Option<i16>,
+    pub typo_ascender: Option<i16>,
+    pub typo_descender: Option<i16>,
+    pub typo_line_gap: Option<i16>,
+    pub win_ascent: Option<u16>,
+    pub win_descent: Option<u16>,
+    pub use_typo_metrics: bool,
+}
+
+impl TableMetrics {
+    pub fn read(typeface: &Typeface) -> TableMetrics {
+        let head = table(typeface, b"head").unwrap_or_default();
+        let hhea = table(typeface, b"hhea").unwrap_or_default();
+        let os2 = table(typeface, b"OS/2").unwrap_or_default();
+        TableMetrics {
+            units_per_em: u16_at(&head, 18).unwrap_or(1000),
+            hhea_ascender: i16_at(&hhea, 4),
+            hhea_descender: i16_at(&hhea, 6),
+            hhea_line_gap: i16_at(&hhea, 8),
+            typo_ascender: i16_at(&os2, 68),
+            typo_descender: i16_at(&os2, 70),
+            typo_line_gap: i16_at(&os2, 72),
+            win_ascent: u16_at(&os2, 74),
+            win_descent: u16_at(&os2, 76),
+            use_typo_metrics: u16_at(&os2, 62)
+                .map(|fs_selection| fs_selection & USE_TYPO_METRICS != 0)
+                .unwrap_or(false),
+        }
+    }
+
+    // (name, ascent, descent, line gap) for every source present in the font, scaled to
+    // `font_size` and using skia's sign convention: ascent negative, descent positive.
+    pub fn sources(&self, font_size: f32) -> Vec<(&'static str, f32, f32, f32)> {
+        let scale = font_size / self.units_per_em as f32;
+        let mut sources = vec![];
+        if let (Some(ascender), Some(descender)) = (self.hhea_ascender, self.hhea_descender) {
+            sources.push((
+                "hhea",
+                -ascender as f32 * scale,
+                -descender as f32 * scale,
+                self.hhea_line_gap.unwrap_or(0) as f32 * scale,
+            ));
+        }
+        if let (Some(ascender), Some(descender)) = (self.typo_ascender, self.typo_descender) {
+            sources.push((
+                "OS/2 typo",
+                -ascender as f32 * scale,
+                -descender as f32 * scale,
+                self.typo_line_gap.unwrap_or(0) as f32 * scale,
+            ));
+        }
+        if let (Some(ascent), Some(descent)) = (self.win_ascent, self.win_descent) {
+            sources.push((
+                "OS/2 win",
+                -(ascent as f32) * scale,
+                descent as f32 * scale,
+                0.0,
+            ));
+        }
+        sources
+    }
+}
+
+// Prints every ascent/descent/line gap source of the font next to what skia reports for a `Font`
+// and for a laid-out `Paragraph` at `font_size`, and names the table source each of them matches.
+pub fn print_metrics_report(file_path: &str, font_size: f32) {
+    let mut fonts = FontEnvironment::new();
+    let family = "Metrics report";
+    let typeface = fonts.register(file_path, family);
+    let table_metrics = TableMetrics::read(&typeface);
+
+    println!(
+        "{} ({}) at {}px, units per em: {}, USE_TYPO_METRICS: {}",
+        file_path,
+        typeface.family_name(),
+        font_size,
+        table_metrics.units_per_em,
+        table_metrics.use_typo_metrics
+    );
+    let sources = table_metrics.sources(font_size);
+    for (name, ascent, descent, line_gap) in sources.iter() {
+        println!(
+            "  {:<10} ascent: {:>10.4}, descent: {:>10.4}, line gap: {:>10.4}",
+            name, ascent, descent, line_gap
+        );
+    }
+
+    let font = Font::from_typeface(typeface, Some(font_size));
+    let (_, font_metrics) = font.metrics();
+    println!(
+        "  {:<10} ascent: {:>10.4}, descent: {:>10.4}, leading:  {:>10.4}  (matches {})",
+        "Font",
+        font_metrics.ascent,
+        font_metrics.descent,
+        font_metrics.leading,
+        closest_source(&sources, font_metrics.ascent, font_metrics.descent)
+    );
+
+    let mut text_style = TextStyle::new();
+    text_style.set_font_size(font_size);
+    text_style.set_font_families(&vec![family]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span("Hg\n", text_style);
+    let paragraph = text.layout(&fonts, 1_000_000_f32);
+    if let Some(line) = paragraph.get_line_metrics().first() {
+        // Line metrics store the ascent as a positive distance above the baseline.
+        let ascent = -line.ascent as f32;
+        let descent = line.descent as f32;
+        println!(
+            "  {:<10} ascent: {:>10.4}, descent: {:>10.4}, height:   {:>10.4}  (matches {})",
+            "Paragraph",
+            ascent,
+            descent,
+            line.height,
+            closest_source(&sources, ascent, descent)
+        );
+    }
+}
+
+fn closest_source(sources: &[(&'static str, f32, f32, f32)], ascent: f32, descent: f32) -> String {
+    sources
+        .iter()
+        .map(|(name, source_ascent, source_descent, _)| {
+            (
+                name,
+                (source_ascent - ascent).abs() + (source_descent - descent).abs(),
+            )
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(name, difference)| {
+            if difference < 0.01 {
+                name.to_string()
+            } else {
+                format!("none, closest is {} off by {:.4}", name, difference)
+            }
+        })
+        .unwrap_or_else(|| "no table metrics".to_string())
+}
@@ -0,0 +1,72 @@
+use crate::data_from_file_path;
+use skia_safe::textlayout::{FontCollection, TypefaceFontProvider};
+use skia_safe::{FontMgr, Typeface};
+use std::path::Path;
+
+// Owns the typeface provider and hands out font collections backed by it, so scenarios don't
+// have to repeat the provider/collection wiring.
+pub struct FontEnvironment {
+    typeface_provider: TypefaceFontProvider,
+    typefaces: Vec<(String, Typeface)>,
+    emoji_family: Option<String>,
+    system_fallback: bool,
+}
+
+impl FontEnvironment {
+    pub fn new() -> Self {
+        FontEnvironment {
+            typeface_provider: TypefaceFontProvider::new(),
+            typefaces: vec![],
+            emoji_family: None,
+            system_fallback: false,
+        }
+    }
+
+    // Lets the collection fall back to system fonts for characters none of the registered fonts
+    // support.
+    pub fn with_system_fallback(mut self) -> Self {
+        self.system_fallback = true;
+        self
+    }
+
+    pub fn register(&mut self, file_path: &str, family: &str) -> Typeface {
+        let typeface = Typeface::from_data(data_from_file_path(Path::new(file_path)), None)
+            .unwrap_or_else(|| panic!("failed to load typeface from {}", file_path));
+        self.register_typeface(typeface.clone(), family);
+        typeface
+    }
+
+    pub fn register_typeface(&mut self, typeface: Typeface, family: &str) {
+        self.typeface_provider
+            .register_typeface(typeface.clone(), Some(family));
+        self.typefaces.push((family.to_string(), typeface));
+    }
+
+    // Registers the font that emoji-presentation clusters are sent to. Text built with this
+    // environment doesn't need to list it in its font families, see `emoji::route_emoji()`.
+    pub fn register_emoji_font(&mut self, file_path: &str, family: &str) -> Typeface {
+        self.emoji_family = Some(family.to_string());
+        self.register(file_path, family)
+    }
+
+    pub fn emoji_family(&self) -> Option<&str> {
+        self.emoji_family.as_deref()
+    }
+
+    // The first typeface registered under `family`. System fonts aren't looked up.
+    pub fn typeface(&self, family: &str) -> Option<&Typeface> {
+        self.typefaces
+            .iter()
+            .find(|(registered_family, _)| registered_family == family)
+            .map(|(_, typeface)| typeface)
+    }
+
+    pub fn font_collection(&self) -> FontCollection {
+        let mut font_collection = FontCollection::new();
+        font_collection.set_asset_font_manager(Some(self.typeface_provider.clone().into()));
+        if self.system_fallback {
+            font_collection.set_default_font_manager(Some(FontMgr::default()), None);
+        }
+        font_collection
+    }
+}
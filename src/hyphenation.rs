@@ -0,0 +1,292 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use skia_safe::textlayout::Paragraph;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+pub const SOFT_HYPHEN: char = '\u{AD}';
+
+// Liang hyphenation patterns for one language, as in TeX's hyph-utf8 pattern files
+// (hyph-en-us.pat.txt etc.).
+pub struct Patterns {
+    // Letters of a pattern to the values between them, one more than there are letters.
+    patterns: HashMap<String, Vec<u8>>,
+    longest: usize,
+    // Whole words with their own break positions, in characters.
+    exceptions: HashMap<String, Vec<usize>>,
+}
+
+impl Patterns {
+    pub fn load(file_path: &str) -> io::Result<Self> {
+        Ok(Patterns::parse(&fs::read_to_string(file_path)?))
+    }
+
+    // Reads whitespace-separated patterns like `.ach4` or `4m1p`. `%` starts a comment, TeX
+    // commands and braces are skipped, and words with hyphens (`as-so-ciate`) are exceptions.
+    pub fn parse(contents: &str) -> Self {
+        let mut patterns = Patterns {
+            patterns: HashMap::new(),
+            longest: 0,
+            exceptions: HashMap::new(),
+        };
+        for line in contents.lines() {
+            let line = line.split('%').next().unwrap_or("");
+            for token in line.split_whitespace() {
+                let token = token.trim_matches(|c| c == '{' || c == '}');
+                if token.is_empty() || token.starts_with('\\') {
+                    continue;
+                }
+                if token.contains('-') {
+                    patterns.add_exception(token);
+                } else {
+                    patterns.add_pattern(token);
+                }
+            }
+        }
+        patterns
+    }
+
+    fn add_pattern(&mut self, pattern: &str) {
+        let mut letters = String::new();
+        let mut values = vec![0];
+        for c in pattern.chars() {
+            match c.to_digit(10) {
+                Some(value) => *values.last_mut().unwrap() = value as u8,
+                None => {
+                    letters.push(c);
+                    values.push(0);
+                }
+            }
+        }
+        self.longest = self.longest.max(letters.chars().count());
+        self.patterns.insert(letters, values);
+    }
+
+    fn add_exception(&mut self, word: &str) {
+        let mut breaks = vec![];
+        let mut letters = String::new();
+        for c in word.chars() {
+            if c == '-' {
+                breaks.push(letters.chars().count());
+            } else {
+                letters.push(c);
+            }
+        }
+        self.exceptions.insert(letters.to_lowercase(), breaks);
+    }
+
+    // Character offsets in `word` where it may be hyphenated, before applying any settings.
+    pub fn breaks(&self, word: &str) -> Vec<usize> {
+        let lowercase = word.to_lowercase();
+        if let Some(breaks) = self.exceptions.get(&lowercase) {
+            return breaks.clone();
+        }
+        let dotted: Vec<char> = format!(".{}.", lowercase).chars().collect();
+        // values[i] sits between dotted[i - 1] and dotted[i].
+        let mut values = vec![0; dotted.len() + 1];
+        for start in 0..dotted.len() {
+            for end in start + 1..=(start + self.longest).min(dotted.len()) {
+                let letters: String = dotted[start..end].iter().collect();
+                if let Some(pattern) = self.patterns.get(&letters) {
+                    for (offset, value) in pattern.iter().enumerate() {
+                        let value_index = start + offset;
+                        values[value_index] = values[value_index].max(*value);
+                    }
+                }
+            }
+        }
+        // A break before character i of the word sits before dotted[i + 1].
+        let length = dotted.len() - 2;
+        (1..length).filter(|i| values[i + 1] % 2 == 1).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HyphenationSettings {
+    // Shorter words are never hyphenated.
+    pub min_word_length: usize,
+    // Characters that must stay before and after a hyphen.
+    pub min_prefix: usize,
+    pub min_suffix: usize,
+}
+
+impl Default for HyphenationSettings {
+    fn default() -> Self {
+        HyphenationSettings {
+            min_word_length: 5,
+            min_prefix: 2,
+            min_suffix: 3,
+        }
+    }
+}
+
+pub struct Hyphenator {
+    locales: HashMap<String, Patterns>,
+    pub settings: HyphenationSettings,
+}
+
+pub struct HyphenatedParagraph {
+    // The text with a hyphen and a newline at every break that was taken at a soft hyphen, so
+    // indices don't match the original text.
+    pub text: StyledText,
+    pub paragraph: Paragraph,
+    pub hyphens: usize,
+    // Soft hyphens taken out again because the hyphen made their line too long.
+    pub dropped: usize,
+}
+
+impl Hyphenator {
+    pub fn new(settings: HyphenationSettings) -> Self {
+        Hyphenator {
+            locales: HashMap::new(),
+            settings,
+        }
+    }
+
+    pub fn load_locale(&mut self, locale: &str, file_path: &str) -> io::Result<()> {
+        let patterns = Patterns::load(file_path)?;
+        self.locales.insert(locale.to_lowercase(), patterns);
+        Ok(())
+    }
+
+    // The patterns for `locale`, falling back from `en-US` to `en`.
+    pub fn patterns(&self, locale: &str) -> Option<&Patterns> {
+        let locale = locale.to_lowercase().replace('_', "-");
+        self.locales.get(&locale).or_else(|| {
+            let language = locale.split('-').next().unwrap_or("");
+            self.locales.get(language)
+        })
+    }
+
+    // Character offsets in `word` to hyphenate at, with the settings applied.
+    pub fn word_breaks(&self, word: &str, patterns: &Patterns) -> Vec<usize> {
+        let length = word.chars().count();
+        if length < self.settings.min_word_length {
+            return vec![];
+        }
+        patterns
+            .breaks(word)
+            .into_iter()
+            .filter(|i| *i >= self.settings.min_prefix && length - i >= self.settings.min_suffix)
+            .collect()
+    }
+
+    // Inserts soft hyphens into the words of the text. A word is hyphenated with the patterns for
+    // the locale of the span it starts in, and may run on into the following spans, e.g. when
+    // part of it is emphasised. Placeholders end words.
+    pub fn insert_soft_hyphens(&self, text: &StyledText) -> StyledText {
+        let characters = text
+            .spans
+            .iter()
+            .enumerate()
+            .flat_map(|(span_index, span)| {
+                let placeholder = span.placeholder.is_some();
+                span.text.char_indices().map(move |(offset, c)| {
+                    (span_index, offset, if placeholder { '\0' } else { c })
+                })
+            })
+            .chain(std::iter::once((text.spans.len(), 0, '\0')));
+        // Span and offset in it of every letter of the current word, and of every soft hyphen.
+        let mut letters: Vec<(usize, usize)> = vec![];
+        let mut word = String::new();
+        let mut hyphens: Vec<(usize, usize)> = vec![];
+        for (span_index, offset, c) in characters {
+            if c.is_alphabetic() {
+                word.push(c);
+                letters.push((span_index, offset));
+                continue;
+            }
+            if let Some((first_span, _)) = letters.first() {
+                if let Some(patterns) = self.patterns(text.spans[*first_span].style.locale()) {
+                    let breaks = self.word_breaks(&word, patterns);
+                    hyphens.extend(breaks.into_iter().map(|index| letters[index]));
+                }
+            }
+            word.clear();
+            letters.clear();
+        }
+        let mut hyphenated = text.clone();
+        // From the end, so the offsets before each insertion stay valid.
+        for (span_index, offset) in hyphens.into_iter().rev() {
+            let span_text = &mut hyphenated.spans[span_index].text;
+            span_text.insert(offset, SOFT_HYPHEN);
+        }
+        hyphenated
+    }
+
+    // Lays `text` out with hyphenation. SkParagraph breaks lines at soft hyphens but doesn't
+    // draw a hyphen there, so line by line from the top, a soft hyphen the line broke at is
+    // replaced by "-\n" and the text laid out again. If the hyphen makes the line too long, the
+    // soft hyphen is dropped instead and the line breaks somewhere else. A forced break ends a
+    // paragraph as far as justification is concerned, so hyphenated lines aren't justified.
+    pub fn layout(
+        &self,
+        text: &StyledText,
+        fonts: &FontEnvironment,
+        width: f32,
+    ) -> HyphenatedParagraph {
+        let mut text = self.insert_soft_hyphens(text);
+        let mut hyphens = 0;
+        let mut dropped = 0;
+        loop {
+            let paragraph = text.layout(fonts, width);
+            let full_text = text.text();
+            let soft_break = paragraph.get_line_metrics().iter().find_map(|line| {
+                let before_break = full_text[..line.end_including_newline].trim_end();
+                if before_break.ends_with(SOFT_HYPHEN) {
+                    Some(before_break.len() - SOFT_HYPHEN.len_utf8())
+                } else {
+                    None
+                }
+            });
+            let index = match soft_break {
+                Some(index) => index,
+                None => {
+                    return HyphenatedParagraph {
+                        text,
+                        paragraph,
+                        hyphens,
+                        dropped,
+                    }
+                }
+            };
+
+            // The hyphen has to fit on the line that broke at the soft hyphen, not be pushed onto
+            // a line of its own with the end of the word.
+            let line_start = paragraph
+                .get_line_metrics()
+                .iter()
+                .find(|line| line.start_index <= index && index < line.end_including_newline)
+                .map(|line| line.start_index)
+                .unwrap_or(0);
+            let mut candidate = text.clone();
+            replace_at(&mut candidate, index, SOFT_HYPHEN.len_utf8(), "-\n");
+            let candidate_paragraph = candidate.layout(fonts, width);
+            let fits = candidate_paragraph.get_line_metrics().iter().any(|line| {
+                line.start_index == line_start && line.end_including_newline == index + 2
+            });
+            if fits {
+                text = candidate;
+                hyphens += 1;
+            } else {
+                replace_at(&mut text, index, SOFT_HYPHEN.len_utf8(), "");
+                dropped += 1;
+            }
+        }
+    }
+}
+
+// Replaces `length` bytes at the UTF-8 `index` of `text.text()`, within the span containing it.
+fn replace_at(text: &mut StyledText, index: usize, length: usize, replacement: &str) {
+    let mut span_start = 0;
+    for span in text.spans.iter_mut() {
+        let span_end = span_start + span.text.len();
+        if index >= span_start && index + length <= span_end {
+            let start = index - span_start;
+            span.text.replace_range(start..start + length, replacement);
+            return;
+        }
+        span_start = span_end;
+    }
+}
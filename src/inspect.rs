@@ -0,0 +1,458 @@
+use crate::sfnt::{
+    cmap_mappings, fixed_at, layout_scripts_and_features, table, tag, tag_name, u16_at, u32_at,
+};
+use crate::unicode_blocks::BLOCKS;
+use skia_safe::{Data, Typeface};
+use std::fs;
+
+// Prints what a font file contains. Usage: `inspect <font file> [--json]`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let file_path = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .ok_or_else(|| "usage: inspect <font file> [--json]".to_string())?;
+    let bytes =
+        fs::read(file_path).map_err(|error| format!("failed to read {}: {}", file_path, error))?;
+    let typeface = Typeface::from_data(Data::new_copy(&bytes), None)
+        .ok_or_else(|| format!("failed to load typeface from {}", file_path))?;
+    let report = FontReport::read(file_path, &typeface);
+    if args.iter().any(|arg| arg == "--json") {
+        println!("{}", report.to_json());
+    } else {
+        println!("{}", report.to_text());
+    }
+    Ok(())
+}
+
+pub struct NameRecord {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub language_id: u16,
+    pub name_id: u16,
+    pub value: String,
+}
+
+pub struct VariationAxis {
+    pub tag: String,
+    pub name: Option<String>,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+}
+
+pub struct BlockCoverage {
+    pub name: &'static str,
+    pub script: &'static str,
+    pub covered: usize,
+    pub size: usize,
+}
+
+pub struct FontReport {
+    pub file_path: String,
+    pub names: Vec<NameRecord>,
+    pub weight_class: Option<u16>,
+    pub width_class: Option<u16>,
+    pub fs_type: Option<u16>,
+    pub units_per_em: Option<u16>,
+    pub tables: Vec<String>,
+    pub glyph_count: usize,
+    pub blocks: Vec<BlockCoverage>,
+    pub scripts: Vec<&'static str>,
+    pub colour_tables: Vec<String>,
+    pub axes: Vec<VariationAxis>,
+    pub gsub_scripts: Vec<String>,
+    pub gsub_features: Vec<String>,
+    pub gpos_scripts: Vec<String>,
+    pub gpos_features: Vec<String>,
+}
+
+impl FontReport {
+    pub fn read(file_path: &str, typeface: &Typeface) -> FontReport {
+        let os2 = table(typeface, b"OS/2").unwrap_or_default();
+        let head = table(typeface, b"head").unwrap_or_default();
+        let names = table(typeface, b"name")
+            .map(|name| read_names(&name))
+            .unwrap_or_default();
+        let mut tables: Vec<String> = typeface
+            .table_tags()
+            .unwrap_or_default()
+            .into_iter()
+            .map(tag_name)
+            .collect();
+        tables.sort();
+
+        let code_points: Vec<u32> = table(typeface, b"cmap")
+            .map(|cmap| cmap_mappings(&cmap))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(code_point, _)| code_point)
+            .collect();
+        let blocks = block_coverage(&code_points);
+        let mut scripts: Vec<&'static str> = blocks.iter().map(|block| block.script).collect();
+        scripts.sort();
+        scripts.dedup();
+
+        let colour_tables = colour_tables(typeface);
+        let axes = table(typeface, b"fvar")
+            .map(|fvar| read_axes(&fvar, &names))
+            .unwrap_or_default();
+        let (gsub_scripts, gsub_features) = table(typeface, b"GSUB")
+            .map(|gsub| layout_scripts_and_features(&gsub))
+            .unwrap_or_default();
+        let (gpos_scripts, gpos_features) = table(typeface, b"GPOS")
+            .map(|gpos| layout_scripts_and_features(&gpos))
+            .unwrap_or_default();
+
+        FontReport {
+            file_path: file_path.to_string(),
+            names,
+            weight_class: u16_at(&os2, 4),
+            width_class: u16_at(&os2, 6),
+            fs_type: u16_at(&os2, 8),
+            units_per_em: u16_at(&head, 18),
+            tables,
+            glyph_count: typeface.count_glyphs(),
+            blocks,
+            scripts,
+            colour_tables,
+            axes,
+            gsub_scripts,
+            gsub_features,
+            gpos_scripts,
+            gpos_features,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!("file: {}", self.file_path)];
+        lines.push("names:".to_string());
+        for name in self.names.iter() {
+            lines.push(format!(
+                "  {} ({}) [platform {}, encoding {}, language 0x{:04X}]: {}",
+                name_id_label(name.name_id),
+                name.name_id,
+                name.platform_id,
+                name.encoding_id,
+                name.language_id,
+                name.value
+            ));
+        }
+        lines.push(format!("weight class: {}", optional(self.weight_class)));
+        lines.push(format!("width class: {}", optional(self.width_class)));
+        lines.push(format!(
+            "fsType: {}",
+            self.fs_type
+                .map(|fs_type| format!("0x{:04X} ({})", fs_type, describe_fs_type(fs_type)))
+                .unwrap_or_else(|| "-".to_string())
+        ));
+        lines.push(format!("units per em: {}", optional(self.units_per_em)));
+        lines.push(format!("glyph count: {}", self.glyph_count));
+        lines.push(format!("tables: {}", self.tables.join(", ")));
+        lines.push(format!(
+            "colour tables: {}",
+            list_or_dash(&self.colour_tables)
+        ));
+        lines.push("variation axes:".to_string());
+        for axis in self.axes.iter() {
+            lines.push(format!(
+                "  {} ({}): {} .. {} .. {}",
+                axis.tag,
+                axis.name.as_deref().unwrap_or("-"),
+                axis.min,
+                axis.default,
+                axis.max
+            ));
+        }
+        lines.push(format!(
+            "GSUB scripts: {}",
+            list_or_dash(&self.gsub_scripts)
+        ));
+        lines.push(format!(
+            "GSUB features: {}",
+            list_or_dash(&self.gsub_features)
+        ));
+        lines.push(format!(
+            "GPOS scripts: {}",
+            list_or_dash(&self.gpos_scripts)
+        ));
+        lines.push(format!(
+            "GPOS features: {}",
+            list_or_dash(&self.gpos_features)
+        ));
+        lines.push(format!("scripts: {}", self.scripts.join(", ")));
+        lines.push("unicode blocks:".to_string());
+        for block in self.blocks.iter() {
+            lines.push(format!(
+                "  {}: {}/{} ({})",
+                block.name, block.covered, block.size, block.script
+            ));
+        }
+        lines.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        let names: Vec<String> = self
+            .names
+            .iter()
+            .map(|name| {
+                format!(
+                    "{{\"nameId\": {}, \"label\": {}, \"platformId\": {}, \"encodingId\": {}, \"languageId\": {}, \"value\": {}}}",
+                    name.name_id,
+                    json_string(name_id_label(name.name_id)),
+                    name.platform_id,
+                    name.encoding_id,
+                    name.language_id,
+                    json_string(&name.value)
+                )
+            })
+            .collect();
+        let axes: Vec<String> = self
+            .axes
+            .iter()
+            .map(|axis| {
+                format!(
+                    "{{\"tag\": {}, \"name\": {}, \"min\": {}, \"default\": {}, \"max\": {}}}",
+                    json_string(&axis.tag),
+                    axis.name
+                        .as_deref()
+                        .map(json_string)
+                        .unwrap_or_else(|| "null".to_string()),
+                    axis.min,
+                    axis.default,
+                    axis.max
+                )
+            })
+            .collect();
+        let blocks: Vec<String> = self
+            .blocks
+            .iter()
+            .map(|block| {
+                format!(
+                    "{{\"name\": {}, \"script\": {}, \"covered\": {}, \"size\": {}}}",
+                    json_string(block.name),
+                    json_string(block.script),
+                    block.covered,
+                    block.size
+                )
+            })
+            .collect();
+        let fields = vec![
+            format!("\"file\": {}", json_string(&self.file_path)),
+            format!("\"names\": [{}]", names.join(", ")),
+            format!("\"weightClass\": {}", json_optional(self.weight_class)),
+            format!("\"widthClass\": {}", json_optional(self.width_class)),
+            format!("\"fsType\": {}", json_optional(self.fs_type)),
+            format!("\"unitsPerEm\": {}", json_optional(self.units_per_em)),
+            format!("\"glyphCount\": {}", self.glyph_count),
+            format!("\"tables\": {}", json_strings(&self.tables)),
+            format!("\"colourTables\": {}", json_strings(&self.colour_tables)),
+            format!("\"variationAxes\": [{}]", axes.join(", ")),
+            format!("\"gsubScripts\": {}", json_strings(&self.gsub_scripts)),
+            format!("\"gsubFeatures\": {}", json_strings(&self.gsub_features)),
+            format!("\"gposScripts\": {}", json_strings(&self.gpos_scripts)),
+            format!("\"gposFeatures\": {}", json_strings(&self.gpos_features)),
+            format!("\"scripts\": {}", json_strings(&self.scripts)),
+            format!("\"unicodeBlocks\": [{}]", blocks.join(", ")),
+        ];
+        format!("{{\n  {}\n}}", fields.join(",\n  "))
+    }
+}
+
+// Colour glyph tables present in the font. COLR is reported with its version since v0 and v1
+// are rendered by different code paths.
+pub fn colour_tables(typeface: &Typeface) -> Vec<String> {
+    let mut colour_tables = vec![];
+    if let Some(colr) = table(typeface, b"COLR") {
+        colour_tables.push(format!("COLRv{}", u16_at(&colr, 0).unwrap_or(0)));
+    }
+    for name in [b"CPAL", b"CBDT", b"CBLC", b"sbix", b"SVG "].iter() {
+        if typeface.get_table_size(tag(name)).unwrap_or(0) > 0 {
+            colour_tables.push(tag_name(tag(name)).trim_end().to_string());
+        }
+    }
+    colour_tables
+}
+
+pub fn read_names(name: &[u8]) -> Vec<NameRecord> {
+    let count = u16_at(name, 2).unwrap_or(0) as usize;
+    let storage = u16_at(name, 4).unwrap_or(0) as usize;
+    let mut records = vec![];
+    for index in 0..count {
+        let record = 6 + index * 12;
+        let fields: Vec<u16> = (0..6)
+            .filter_map(|field| u16_at(name, record + field * 2))
+            .collect();
+        if fields.len() < 6 {
+            break;
+        }
+        let start = storage + fields[5] as usize;
+        let bytes = match name.get(start..start + fields[4] as usize) {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let value = match fields[0] {
+            // Unicode and Windows platforms store UTF-16BE.
+            0 | 3 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            // Mac Roman. Names are practically always ASCII, so treat it as Latin-1.
+            _ => bytes.iter().map(|byte| *byte as char).collect(),
+        };
+        records.push(NameRecord {
+            platform_id: fields[0],
+            encoding_id: fields[1],
+            language_id: fields[2],
+            name_id: fields[3],
+            value,
+        });
+    }
+    records
+}
+
+// English Windows name, falling back to any record with the id.
+pub fn find_name(names: &[NameRecord], name_id: u16) -> Option<String> {
+    names
+        .iter()
+        .filter(|record| record.name_id == name_id)
+        .max_by_key(|record| (record.platform_id == 3, record.language_id == 0x409))
+        .map(|record| record.value.clone())
+}
+
+fn read_axes(fvar: &[u8], names: &[NameRecord]) -> Vec<VariationAxis> {
+    let axes_offset = u16_at(fvar, 4).unwrap_or(0) as usize;
+    let axis_count = u16_at(fvar, 8).unwrap_or(0) as usize;
+    let axis_size = u16_at(fvar, 10).unwrap_or(20) as usize;
+    (0..axis_count)
+        .filter_map(|index| {
+            let axis = axes_offset + index * axis_size;
+            Some(VariationAxis {
+                tag: tag_name(u32_at(fvar, axis)?),
+                min: fixed_at(fvar, axis + 4)?,
+                default: fixed_at(fvar, axis + 8)?,
+                max: fixed_at(fvar, axis + 12)?,
+                name: u16_at(fvar, axis + 18).and_then(|name_id| find_name(names, name_id)),
+            })
+        })
+        .collect()
+}
+
+fn block_coverage(code_points: &[u32]) -> Vec<BlockCoverage> {
+    BLOCKS
+        .iter()
+        .filter_map(|&(start, end, name, script)| {
+            let first = code_points.partition_point(|code_point| *code_point < start);
+            let last = code_points.partition_point(|code_point| *code_point <= end);
+            if last > first {
+                Some(BlockCoverage {
+                    name,
+                    script,
+                    covered: last - first,
+                    size: (end - start + 1) as usize,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn name_id_label(name_id: u16) -> &'static str {
+    match name_id {
+        0 => "copyright",
+        1 => "family",
+        2 => "subfamily",
+        3 => "unique id",
+        4 => "full name",
+        5 => "version",
+        6 => "PostScript name",
+        7 => "trademark",
+        8 => "manufacturer",
+        9 => "designer",
+        10 => "description",
+        11 => "vendor URL",
+        12 => "designer URL",
+        13 => "license",
+        14 => "license URL",
+        16 => "typographic family",
+        17 => "typographic subfamily",
+        18 => "compatible full name",
+        19 => "sample text",
+        20 => "PostScript CID findfont name",
+        21 => "WWS family",
+        22 => "WWS subfamily",
+        23 => "light background palette",
+        24 => "dark background palette",
+        25 => "variations PostScript name prefix",
+        _ => "other",
+    }
+}
+
+fn describe_fs_type(fs_type: u16) -> String {
+    let mut flags = vec![];
+    match fs_type & 0x000F {
+        0 => flags.push("installable"),
+        0x0002 => flags.push("restricted license"),
+        0x0004 => flags.push("preview & print"),
+        0x0008 => flags.push("editable"),
+        _ => flags.push("invalid usage permissions"),
+    }
+    if fs_type & 0x0100 != 0 {
+        flags.push("no subsetting");
+    }
+    if fs_type & 0x0200 != 0 {
+        flags.push("bitmap embedding only");
+    }
+    flags.join(", ")
+}
+
+fn optional(value: Option<u16>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn list_or_dash(values: &[String]) -> String {
+    if values.is_empty() {
+        "-".to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+fn json_optional(value: Option<u16>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+fn json_strings<S: AsRef<str>>(values: &[S]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| json_string(value.as_ref()))
+        .collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if (character as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,194 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use crate::text_on_path::layout_clusters;
+use skia_safe::Rect;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+// Invisible characters SkParagraph's (ICU's) line breaking respects: a break opportunity and a
+// break prohibition.
+pub const ZERO_WIDTH_SPACE: char = '\u{200B}';
+pub const WORD_JOINER: char = '\u{2060}';
+
+// Where lines may break on top of the default UAX #14 rules. Applied by inserting break hints
+// into the text before it reaches the ParagraphBuilder, so the text of the result has them too.
+#[derive(Clone, Debug)]
+pub struct LineBreakPolicy {
+    // Characters lines may break after, when followed by something other than a space.
+    pub break_after: Vec<char>,
+    // Never break inside words starting with http://, https:// or www.
+    pub protect_urls: bool,
+    // Words wider than the layout width get a break opportunity only where a line of them would
+    // overflow, so that shaping across the rest of the word (kerning, Arabic joining, Indic
+    // conjuncts) is left alone. URLs stay whole when protected.
+    pub break_long_words: bool,
+}
+
+impl Default for LineBreakPolicy {
+    fn default() -> Self {
+        LineBreakPolicy {
+            break_after: vec!['/'],
+            protect_urls: true,
+            break_long_words: true,
+        }
+    }
+}
+
+impl LineBreakPolicy {
+    // `text` with break hints inserted for laying it out at `width`.
+    pub fn apply(&self, text: &StyledText, fonts: &FontEnvironment, width: f32) -> StyledText {
+        let full_text = text.text();
+        let urls = if self.protect_urls {
+            url_ranges(&full_text)
+        } else {
+            vec![]
+        };
+        let in_url = |index: usize| urls.iter().any(|url| url.start < index && index < url.end);
+
+        let mut hints = BTreeMap::new();
+        let characters: Vec<(usize, char)> = full_text.char_indices().collect();
+        for pair in characters.windows(2) {
+            let ((_, character), (next_index, next)) = (pair[0], pair[1]);
+            if self.break_after.contains(&character)
+                && !next.is_whitespace()
+                && !self.break_after.contains(&next)
+                && !in_url(next_index)
+            {
+                hints.insert(next_index, ZERO_WIDTH_SPACE);
+            }
+        }
+        for url in urls.iter() {
+            for (offset, _) in full_text[url.clone()].char_indices().skip(1) {
+                hints.insert(url.start + offset, WORD_JOINER);
+            }
+        }
+
+        if self.break_long_words {
+            let paragraph = text.layout(fonts, 1_000_000_f32);
+            let mut clusters = vec![];
+            let mut span_start = 0;
+            for span in text.spans.iter() {
+                if span.placeholder.is_none() {
+                    for (range, rect) in
+                        layout_clusters(&paragraph, &full_text, span_start, &span.text)
+                    {
+                        clusters.push((span_start + range.start..span_start + range.end, rect));
+                    }
+                }
+                span_start += span.text.len();
+            }
+
+            let mut long_word_breaks = vec![];
+            let mut word: Vec<&(Range<usize>, Rect)> = vec![];
+            for cluster in clusters.iter() {
+                let is_space = full_text[cluster.0.clone()]
+                    .chars()
+                    .all(char::is_whitespace);
+                let starts_word = is_space
+                    || hints.get(&cluster.0.start) == Some(&ZERO_WIDTH_SPACE)
+                    || word
+                        .last()
+                        .map(|last| last.0.end != cluster.0.start)
+                        .unwrap_or(false);
+                if starts_word {
+                    long_word_breaks.extend(self.long_word_breaks(&word, width, &in_url));
+                    word.clear();
+                }
+                if !is_space {
+                    word.push(cluster);
+                }
+            }
+            long_word_breaks.extend(self.long_word_breaks(&word, width, &in_url));
+            for index in long_word_breaks {
+                hints.insert(index, ZERO_WIDTH_SPACE);
+            }
+        }
+
+        insert_hints(text, &hints)
+    }
+
+    // The cluster boundaries inside `word` where a line holding it would overflow `width`,
+    // taking the word to start a line. A cluster wider than the line gets one to itself.
+    fn long_word_breaks(
+        &self,
+        word: &[&(Range<usize>, Rect)],
+        width: f32,
+        in_url: &impl Fn(usize) -> bool,
+    ) -> Vec<usize> {
+        let mut breaks = vec![];
+        // Extent of the word's part on the current line, which works for either direction.
+        let (mut left, mut right) = match word.first() {
+            Some(cluster) => (cluster.1.left, cluster.1.right),
+            None => return breaks,
+        };
+        for cluster in word[1..].iter() {
+            let (extended_left, extended_right) =
+                (left.min(cluster.1.left), right.max(cluster.1.right));
+            if extended_right - extended_left > width && !in_url(cluster.0.start) {
+                breaks.push(cluster.0.start);
+                left = cluster.1.left;
+                right = cluster.1.right;
+            } else {
+                left = extended_left;
+                right = extended_right;
+            }
+        }
+        breaks
+    }
+}
+
+// Words starting with a URL scheme or www., without trailing punctuation.
+fn url_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut urls = vec![];
+    let mut word_start = None;
+    for (index, character) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (word_start, character.is_whitespace()) {
+            (None, false) => word_start = Some(index),
+            (Some(start), true) => {
+                let word = &text[start..index];
+                if word.starts_with("http://")
+                    || word.starts_with("https://")
+                    || word.starts_with("www.")
+                {
+                    let url = word.trim_end_matches(|c| ".,;:!?)\"'".contains(c));
+                    urls.push(start..start + url.len());
+                }
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+    urls
+}
+
+// Inserts each hint before the character at its UTF-8 index in `text.text()`. Placeholders are
+// left alone.
+fn insert_hints(text: &StyledText, hints: &BTreeMap<usize, char>) -> StyledText {
+    let mut hinted = text.clone();
+    let mut span_start = 0;
+    for span in hinted.spans.iter_mut() {
+        let span_length = span.text.len();
+        if span.placeholder.is_none() {
+            let mut span_text = String::with_capacity(span_length);
+            for (offset, character) in span.text.char_indices() {
+                if let Some(hint) = hints.get(&(span_start + offset)) {
+                    span_text.push(*hint);
+                }
+                span_text.push(character);
+            }
+            span.text = span_text;
+        }
+        span_start += span_length;
+    }
+    hinted
+}
+
+// `text` without the hints inserted by `LineBreakPolicy::apply()`, e.g. for copying it.
+pub fn strip_break_hints(text: &str) -> String {
+    text.chars()
+        .filter(|c| *c != ZERO_WIDTH_SPACE && *c != WORD_JOINER)
+        .collect()
+}
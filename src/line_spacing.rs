@@ -0,0 +1,67 @@
+use crate::styled_text::StyledText;
+use skia_safe::textlayout::{StrutStyle, TextHeightBehavior, TextStyle};
+
+pub struct Strut {
+    pub font_families: Vec<String>,
+    pub font_size: f32,
+    pub height_multiplier: Option<f32>,
+    // Makes every line exactly as tall as the strut, even when a glyph is taller.
+    pub force_height: bool,
+}
+
+#[derive(Default)]
+pub struct LineSpacing {
+    // Line height as a multiple of the font size. Without it the font's own ascent, descent and
+    // line gap are used.
+    pub height_multiplier: Option<f32>,
+    // Splits the extra space added by the multiplier evenly above and below the glyphs instead of
+    // distributing it proportionally to ascent and descent.
+    pub half_leading: bool,
+    pub strut: Option<Strut>,
+    pub trim_first_line_ascent: bool,
+    pub trim_last_line_descent: bool,
+}
+
+impl LineSpacing {
+    pub fn text_height_behavior(&self) -> TextHeightBehavior {
+        match (self.trim_first_line_ascent, self.trim_last_line_descent) {
+            (false, false) => TextHeightBehavior::All,
+            (true, false) => TextHeightBehavior::DisableFirstAscent,
+            (false, true) => TextHeightBehavior::DisableLastDescent,
+            (true, true) => TextHeightBehavior::DisableAll,
+        }
+    }
+
+    pub fn apply(&self, text: &mut StyledText) {
+        let mut default_style = text.paragraph_style.text_style().clone();
+        self.apply_to_style(&mut default_style);
+        text.paragraph_style.set_text_style(&default_style);
+        for span in text.spans.iter_mut() {
+            self.apply_to_style(&mut span.style);
+        }
+
+        text.paragraph_style
+            .set_text_height_behavior(self.text_height_behavior());
+        if let Some(strut) = &self.strut {
+            let mut strut_style = StrutStyle::new();
+            strut_style.set_strut_enabled(true);
+            strut_style.set_font_families(&strut.font_families);
+            strut_style.set_font_size(strut.font_size);
+            if let Some(height_multiplier) = strut.height_multiplier {
+                strut_style.set_height(height_multiplier);
+                strut_style.set_height_override(true);
+            }
+            strut_style.set_half_leading(self.half_leading);
+            strut_style.set_force_strut_height(strut.force_height);
+            text.paragraph_style.set_strut_style(strut_style);
+        }
+    }
+
+    fn apply_to_style(&self, style: &mut TextStyle) {
+        if let Some(height_multiplier) = self.height_multiplier {
+            style.set_height(height_multiplier);
+            style.set_height_override(true);
+        }
+        style.set_half_leading(self.half_leading);
+    }
+}
@@ -1,17 +1,105 @@
+mod animation;
+mod auto_fit;
+mod colour_fonts;
+mod conformance;
+mod decoration;
+mod emoji;
+mod emoji_data;
+mod font_metrics;
+mod fonts;
+mod hyphenation;
+mod inspect;
+mod line_breaks;
+mod line_spacing;
+mod placeholders;
+mod render_diff;
+mod sfnt;
+mod styled_text;
+mod subset;
+mod text_effects;
+mod text_flow;
+mod text_index;
+mod text_on_path;
+mod truncation;
+mod unicode_blocks;
+mod warp;
+mod whitespace;
+mod woff2;
+
 use std::cell::RefCell;
 use skia_safe::paint::Style;
-use skia_safe::textlayout::{FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, RectHeightStyle, RectWidthStyle, TextAlign, TextDirection, TextHeightBehavior, TextStyle, TypefaceFontProvider};
-use skia_safe::{Color, Data, Font, FontMgr, FontStyle, ISize, Paint, Surface, TextBlob, Typeface};
+use skia_safe::textlayout::{FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, PlaceholderAlignment, RectHeightStyle, RectWidthStyle, TextAlign, TextDecoration, TextDecorationStyle, TextDirection, TextHeightBehavior, TextStyle, TypefaceFontProvider};
+use skia_safe::{AlphaType, Canvas, Color, ColorType, Data, Font, FontMgr, FontStyle, IRect, ISize, ImageInfo, Paint, Surface, TextBlob, Typeface};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::ops::Range;
 use std::path::Path;
 
+use animation::{render_frames, Animation};
+use auto_fit::{fit_font_size, FitOptions};
+use decoration::{Decoration, DecorationMetrics};
+use emoji::Presentation;
+use fonts::FontEnvironment;
+use hyphenation::{HyphenationSettings, Hyphenator};
+use line_breaks::{strip_break_hints, LineBreakPolicy};
+use line_spacing::{LineSpacing, Strut};
+use placeholders::{paint_placeholders, placeholder_rects, Placeholder};
+use styled_text::StyledText;
+use text_effects::{
+    ink_bounds, paint_with_effects, Fill, Glow, Shadow, Stroke, StrokeAlign, TextEffect,
+};
+use text_flow::{columns, flow};
+use text_on_path::{
+    layout_on_path, path_guide_paint, PathAlign, PathOverflow, PathSide, PathTextOptions,
+};
+use truncation::{layout_truncated, Truncation};
+use warp::{warp_text, Warp, WarpMode};
+use whitespace::{bounding_width, measure_trailing_whitespace, paint_aligned, TrailingWhitespace};
+
 fn main() {
-    twemoji_measuring();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("inspect") => {
+            if let Err(reason) = inspect::run(&args[1..]) {
+                println!("{}", reason);
+            }
+        }
+        Some("woff2") => match args.get(1) {
+            Some(file_path) => {
+                match woff2::convert(Path::new(file_path), args.get(2).map(Path::new)) {
+                    Ok(output_path) => println!("wrote {}", output_path.display()),
+                    Err(reason) => println!("{}", reason),
+                }
+            }
+            None => println!("usage: woff2 <font.woff2> [output path]"),
+        },
+        Some("subset") => match (args.get(1), args.get(2), args.get(3)) {
+            (Some(file_path), Some(text), Some(output_path)) => {
+                match subset::write_subset(Path::new(file_path), text, Path::new(output_path)) {
+                    Ok(report) => print_subset_report(&report),
+                    Err(reason) => println!("{}", reason),
+                }
+            }
+            _ => println!("usage: subset <font file> <text> <output path>"),
+        },
+        Some("line-break-test") => conformance::run_line_break_test(&args[1..]),
+        Some("grapheme-break-test") => conformance::run_grapheme_break_test(&args[1..]),
+        Some("word-break-test") => conformance::run_word_break_test(&args[1..]),
+        _ => twemoji_measuring(),
+    }
 }
 
 fn twemoji_measuring() {
+    let mut surface = twemoji_measuring_with_font("Twemoji.Mozilla.ttf");
+    save_png(&mut surface, "output/twemoji_transparent.png");
+    save_png_over(
+        &mut surface,
+        "output/twemoji.png",
+        Backdrop::Solid(Color::from_rgb(0, 255, 0)),
+    );
+}
+
+fn twemoji_measuring_with_font(font_path: &str) -> Surface {
     let canvas_width = 1080;
 
     let mut surface = Surface::new_raster_n32_premul(ISize::new(canvas_width, 1080)).unwrap();
@@ -22,8 +110,7 @@ fn twemoji_measuring() {
 
     let provider_ref = RefCell::new(typeface_provider);
     let mut typeface_provider = provider_ref.borrow_mut();
-    let font =
-        Typeface::from_data(data_from_file_path(Path::new("Twemoji.Mozilla.ttf")), None).unwrap();
+    let font = Typeface::from_data(data_from_file_path(Path::new(font_path)), None).unwrap();
     typeface_provider.register_typeface(font.clone(), Some("YAFbtwemoji-0-Normal-Normal"));
 
     let text = "❤\n";
@@ -37,7 +124,7 @@ fn twemoji_measuring() {
 
     let mut paragraph_style = ParagraphStyle::new();
     paragraph_style.set_text_direction(TextDirection::LTR);
-    paragraph_style.set_text_align(TextAlign::Left);
+    paragraph_style.set_text_align(TextAlign::Right);
     paragraph_style.set_text_height_behavior(TextHeightBehavior::DisableAll);
     let mut text_style = TextStyle::new();
     text_style.set_letter_spacing(0.0);
@@ -55,16 +142,1432 @@ fn twemoji_measuring() {
     builder.add_text(text);
     builder.pop();
     let mut paragraph = builder.build();
-    paragraph.layout(1_000_000_f32);
-
-    let line_width = paragraph.get_line_metrics().first().unwrap().width;
-    let ascent = paragraph.get_line_metrics().first().unwrap().ascent;
-    println!("ascent: {}", ascent);
+    // Right alignment within the canvas comes from the paragraph itself.
+    paragraph.layout(canvas_width as f32);
+
+    let line_metrics = paragraph.get_line_metrics();
+    let line = line_metrics.first().unwrap();
+    println!(
+        "ascent: {}, left: {}, width: {}",
+        line.ascent, line.left, line.width
+    );
 
-    let point = skia_safe::Point::new(canvas_width as f32 - line_width as f32, 0.0);
-    surface.canvas().clear(Color::from_rgb(0, 255, 0));
+    let point = skia_safe::Point::new(0.0, 0.0);
+    surface.canvas().clear(Color::TRANSPARENT);
     paragraph.paint(surface.canvas(), point);
-    save_png(&mut surface, "output/twemoji.png");
+    surface
+}
+
+fn transparent_background() {
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(320, 240)).unwrap();
+    let mut style = ParagraphStyle::new();
+    let mut text_style = TextStyle::new();
+    // Semi-transparent text is where premultiplied readback goes wrong, so use it here.
+    text_style.set_color(Color::from_argb(128, 200, 0, 0));
+    text_style.set_font_size(48.0);
+    text_style.set_font_families(&vec!["Open Sans"]);
+    style.set_text_style(&text_style);
+    let mut typeface_provider = TypefaceFontProvider::new();
+    let open_sans =
+        Typeface::from_data(data_from_file_path(Path::new("OpenSans-Regular.ttf")), None).unwrap();
+    typeface_provider.register_typeface(open_sans, Some("Open Sans"));
+    let mut font_collection = FontCollection::new();
+    font_collection.set_asset_font_manager(Some(typeface_provider.clone().into()));
+    let mut paragraph_builder = ParagraphBuilder::new(&style, font_collection);
+    paragraph_builder.add_text("Lorem ipsum ❤\n");
+    let mut paragraph = paragraph_builder.build();
+    paragraph.layout(320.0);
+    surface.canvas().clear(Color::TRANSPARENT);
+    paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+    save_png(&mut surface, "output/transparent_background.png");
+    save_png_over(
+        &mut surface,
+        "output/transparent_background_checkerboard.png",
+        Backdrop::Checkerboard(8),
+    );
+    save_png_over(
+        &mut surface,
+        "output/transparent_background_green.png",
+        Backdrop::Solid(Color::from_rgb(0, 255, 0)),
+    );
+}
+
+fn auto_fit() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+    fonts.register("OpenSans-Light.ttf", "Open Sans Light");
+
+    let mut style = ParagraphStyle::new();
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(40.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style.clone());
+    text.add_span("Lorem ipsum ", text_style.clone());
+    let mut bold_style = text_style.clone();
+    bold_style.set_font_style(FontStyle::bold());
+    bold_style.set_font_size(60.0);
+    text.add_span("dolor sit amet\n", bold_style);
+
+    let (box_width, box_height) = (300.0, 120.0);
+    let options = FitOptions {
+        min_font_size: 6.0,
+        max_lines: Some(3),
+        ..FitOptions::default()
+    };
+    let result = fit_font_size(&text, &fonts, box_width, box_height, &options);
+    println!(
+        "font size: {}, fits: {}, lines: {}, height: {}",
+        result.font_size, result.fits, result.line_count, result.height
+    );
+
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(320, 240)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    let mut paint = Paint::default();
+    paint.set_style(Style::Stroke);
+    paint.set_color(Color::from_rgb(255, 0, 0));
+    surface.canvas().draw_rect(
+        skia_safe::Rect::from_xywh(0.0, 0.0, box_width, box_height),
+        &paint,
+    );
+    let paragraph = text
+        .with_font_size(result.font_size)
+        .layout(&fonts, box_width);
+    paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+    save_png(&mut surface, "output/auto_fit.png");
+
+    // At the width of disappearing_letter() the "1" vanishes at 26.6667px although the line
+    // metrics still cover it, so the search has to skip that size.
+    let mut digit_style = TextStyle::new();
+    digit_style.set_color(Color::from_rgb(0, 0, 0));
+    digit_style.set_font_size(26.6667);
+    digit_style.set_font_families(&vec!["Open Sans Light"]);
+    let mut digit_paragraph_style = ParagraphStyle::new();
+    digit_paragraph_style.set_text_style(&digit_style);
+    let mut digit = StyledText::new(digit_paragraph_style);
+    digit.add_span("1\n", digit_style);
+    let digit_options = FitOptions {
+        min_font_size: 20.0,
+        max_font_size: 26.6667,
+        ..FitOptions::default()
+    };
+    for width in [15.0, 15.218, 15.5].iter() {
+        let result = fit_font_size(&digit, &fonts, *width, 320.0, &digit_options);
+        println!(
+            "disappearing letter at width {}: font size {}, fits: {}, 26.6667 skipped: {}",
+            width,
+            result.font_size,
+            result.fits,
+            result.font_size < digit_options.max_font_size
+        );
+    }
+}
+
+fn ellipsis_truncation() {
+    let mut fonts = FontEnvironment::new().with_system_fallback();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+    fonts.register("Twemoji.Mozilla.ttf", "Twemoji");
+
+    let mut style = ParagraphStyle::new();
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(24.0);
+    text_style.set_font_families(&vec!["OpenSans", "Twemoji"]);
+    style.set_text_style(&text_style);
+    let mut italic_style = text_style.clone();
+    italic_style.set_font_style(FontStyle::italic());
+    italic_style.set_color(Color::from_rgb(0, 0, 200));
+
+    let mut two_spans = StyledText::new(style.clone());
+    two_spans.add_span("Lorem ipsum dolor ", text_style.clone());
+    two_spans.add_span("sit amet, consectetur adipiscing elit\n", italic_style);
+    // Fits into two lines exactly, the trailing newline must not produce an ellipsis.
+    let mut trailing_newline = StyledText::new(style.clone());
+    trailing_newline.add_span("Lorem ipsum\ndolor sit\n", text_style.clone());
+    let mut emoji = StyledText::new(style.clone());
+    emoji.add_span("Family 👨‍👩‍👧‍👦👨‍👩‍👧‍👦👨‍👩‍👧‍👦👨‍👩‍👧‍👦👨‍👩‍👧‍👦👨‍👩‍👧‍👦\n", text_style.clone());
+    let mut rtl_style = style.clone();
+    rtl_style.set_text_direction(TextDirection::RTL);
+    let mut rtl = StyledText::new(rtl_style);
+    rtl.add_span(
+        "שלום עולם, זהו טקסט ארוך מאוד שלא נכנס\n",
+        text_style.clone(),
+    );
+
+    let truncation = Truncation {
+        max_lines: 2,
+        ellipsis: "…".to_string(),
+    };
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(320, 640)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    let mut top = 0.0;
+    for (name, text) in vec![
+        ("two spans", two_spans),
+        ("trailing newline", trailing_newline),
+        ("emoji", emoji),
+        ("rtl", rtl),
+    ] {
+        let truncated = layout_truncated(&text, &fonts, 240.0, &truncation);
+        let report = &truncated.report;
+        let full_text = text.text();
+        println!(
+            "{}: truncated: {}, lines: {}, last visible index: {}, visible text: {:?}",
+            name,
+            report.truncated,
+            report.line_count,
+            report.last_visible_index,
+            &full_text[..report.last_visible_index]
+        );
+        truncated
+            .paragraph
+            .paint(surface.canvas(), skia_safe::Point::new(0.0, top));
+        top += truncated.paragraph.height() + 20.0;
+    }
+    save_png(&mut surface, "output/ellipsis_truncation.png");
+}
+
+fn text_alignment_matrix() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    // Same text, size and letter spacing as multi_line_end_spaces(), plus a line that ends with a
+    // space of a different style.
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(18.666666666666668);
+    text_style.set_letter_spacing(1.8666666666666668);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut bold_style = text_style.clone();
+    bold_style.set_font_style(FontStyle::bold());
+
+    let alignments = vec![
+        ("left", TextAlign::Left),
+        ("right", TextAlign::Right),
+        ("center", TextAlign::Center),
+        ("justify", TextAlign::Justify),
+        ("start", TextAlign::Start),
+        ("end", TextAlign::End),
+    ];
+    let layout_width = 242.48;
+    let cell_height = 140.0;
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(560, 6 * 140)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    let mut box_paint = Paint::default();
+    box_paint.set_style(Style::Stroke);
+    box_paint.set_color(Color::from_rgb(255, 0, 0));
+
+    for (row, (name, align)) in alignments.into_iter().enumerate() {
+        for (column, direction) in vec![TextDirection::LTR, TextDirection::RTL]
+            .into_iter()
+            .enumerate()
+        {
+            let mut style = ParagraphStyle::new();
+            style.set_text_style(&text_style);
+            style.set_text_align(align);
+            style.set_text_direction(direction);
+            let mut text = StyledText::new(style);
+            text.add_span("Two lines with spaces  at the ends ", text_style.clone());
+            text.add_span(" \nlast line\n", bold_style.clone());
+            let paragraph = text.layout(&fonts, layout_width);
+
+            for (index, line) in paragraph.get_line_metrics().iter().enumerate() {
+                let right = line.left + line.width;
+                println!(
+                    "{} {:?} line {}: left: {}, right: {}, gap to layout width: {}",
+                    name,
+                    direction,
+                    index,
+                    line.left,
+                    right,
+                    layout_width as f64 - right
+                );
+            }
+
+            let origin = skia_safe::Point::new(
+                10.0 + column as f32 * (layout_width + 40.0),
+                row as f32 * cell_height + 10.0,
+            );
+            surface.canvas().draw_rect(
+                skia_safe::Rect::from_xywh(origin.x, origin.y, layout_width, paragraph.height()),
+                &box_paint,
+            );
+            paragraph.paint(surface.canvas(), origin);
+        }
+    }
+    save_png(&mut surface, "output/text_alignment_matrix.png");
+}
+
+fn line_spacing_matrix() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("Jua.woff2", "Jua");
+    fonts.register("NotoSans-Regular.ttf", "Noto Sans");
+    fonts.register("Twemoji.Mozilla.ttf", "Twemoji");
+
+    let options = vec![
+        ("font metrics", LineSpacing::default()),
+        (
+            "height 1.5",
+            LineSpacing {
+                height_multiplier: Some(1.5),
+                ..LineSpacing::default()
+            },
+        ),
+        (
+            "height 1.5, half leading",
+            LineSpacing {
+                height_multiplier: Some(1.5),
+                half_leading: true,
+                ..LineSpacing::default()
+            },
+        ),
+        (
+            "forced strut",
+            LineSpacing {
+                strut: Some(Strut {
+                    font_families: vec!["Noto Sans".to_string()],
+                    font_size: 32.0,
+                    height_multiplier: Some(1.2),
+                    force_height: true,
+                }),
+                ..LineSpacing::default()
+            },
+        ),
+        (
+            "trim first ascent and last descent",
+            LineSpacing {
+                trim_first_line_ascent: true,
+                trim_last_line_descent: true,
+                ..LineSpacing::default()
+            },
+        ),
+    ];
+    let families = vec!["Jua", "Noto Sans", "Twemoji"];
+
+    let cell_width = 220.0;
+    let cell_height = 240.0;
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(
+        (cell_width * families.len() as f32) as i32,
+        (cell_height * options.len() as f32) as i32,
+    ))
+    .unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    let mut baseline_paint = Paint::default();
+    baseline_paint.set_color(Color::from_rgb(255, 0, 0));
+    let mut bounds_paint = Paint::default();
+    bounds_paint.set_style(Style::Stroke);
+    bounds_paint.set_color(Color::from_rgb(0, 0, 255));
+
+    for (row, (name, line_spacing)) in options.iter().enumerate() {
+        for (column, family) in families.iter().enumerate() {
+            let mut text_style = TextStyle::new();
+            text_style.set_color(Color::from_rgb(0, 0, 0));
+            text_style.set_font_size(32.0);
+            text_style.set_font_families(&vec![*family]);
+            let mut style = ParagraphStyle::new();
+            style.set_text_style(&text_style);
+            let mut text = StyledText::new(style);
+            text.add_span("Ág ❤\nÁg ❤\n", text_style);
+            text.set_line_spacing(line_spacing);
+            let paragraph = text.layout(&fonts, cell_width);
+
+            let origin =
+                skia_safe::Point::new(column as f32 * cell_width, row as f32 * cell_height);
+            for line in paragraph.get_line_metrics().iter() {
+                println!(
+                    "{} / {}: line {} baseline: {}, ascent: {}, descent: {}, height: {}",
+                    name,
+                    family,
+                    line.line_number,
+                    line.baseline,
+                    line.ascent,
+                    line.descent,
+                    line.height
+                );
+                let baseline = origin.y + line.baseline as f32;
+                surface.canvas().draw_line(
+                    (origin.x, baseline),
+                    (origin.x + cell_width, baseline),
+                    &baseline_paint,
+                );
+            }
+            surface.canvas().draw_rect(
+                skia_safe::Rect::from_xywh(origin.x, origin.y, cell_width, paragraph.height()),
+                &bounds_paint,
+            );
+            paragraph.paint(surface.canvas(), origin);
+        }
+    }
+    save_png(&mut surface, "output/line_spacing_matrix.png");
+}
+
+fn woff2_against_sibling() {
+    let fonts = vec![
+        ("Arimo.woff2", None),
+        ("Aileron.woff2", None),
+        ("Jua.woff2", None),
+        ("Adlery.woff2", None),
+        ("LeagueSpartan.woff2", None),
+        ("OpenSans-Light.woff2", Some("OpenSans-Light.ttf")),
+        ("NotoSans-Regular.woff2", Some("NotoSans-Regular.ttf")),
+        ("Adigiana_Ultra.woff2", Some("Adigiana_Ultra.ttf")),
+        (
+            "NotoSansDevanagari-Regular.woff2",
+            Some("NotoSansDevanagari-Regular.ttf"),
+        ),
+    ];
+    for (woff2_file, sibling) in fonts {
+        if let Err(reason) =
+            woff2::verify_against_sibling(Path::new(woff2_file), sibling.map(Path::new))
+        {
+            println!("{}", reason);
+        }
+    }
+}
+
+fn subset_repro() {
+    // Re-runs twemoji_measuring() against a subset of the emoji font and checks that the output
+    // is unchanged, so the subset can be attached to a bug report instead of the full font.
+    let font_path = "Twemoji-14.0.2.ttf";
+    let subset_path = "output/Twemoji-14.0.2-subset.ttf";
+    match subset::write_subset(Path::new(font_path), "❤\n", Path::new(subset_path)) {
+        Ok(report) => {
+            print_subset_report(&report);
+            println!(
+                "{}: {} bytes before subsetting, {} bytes after",
+                font_path, report.original_size, report.subset_size
+            );
+        }
+        Err(reason) => {
+            println!("{}", reason);
+            return;
+        }
+    }
+    let mut original = twemoji_measuring_with_font(font_path);
+    let mut subset_surface = twemoji_measuring_with_font(subset_path);
+    save_png(&mut subset_surface, "output/twemoji_subset.png");
+    let (comparison, diff) = render_diff::compare(
+        &read_rgba_pixels(&mut original).unwrap(),
+        &read_rgba_pixels(&mut subset_surface).unwrap(),
+    );
+    if comparison.identical() {
+        println!("the subset renders identically");
+    } else {
+        println!(
+            "the subset renders differently: {} pixels, max channel difference {}",
+            comparison.differing_pixels, comparison.max_channel_difference
+        );
+        save_rgba_png(
+            &diff,
+            subset_surface.width(),
+            subset_surface.height(),
+            "output/twemoji_subset_diff.png",
+        );
+    }
+}
+
+fn print_subset_report(report: &subset::SubsetReport) {
+    println!(
+        "kept {} of {} glyphs, {} -> {} bytes",
+        report.glyphs_kept, report.glyph_count, report.original_size, report.subset_size
+    );
+    for warning in report.warnings.iter() {
+        println!("warning: {}", warning);
+    }
+}
+
+fn emoji_presentation() {
+    let mut fonts = FontEnvironment::new().with_system_fallback();
+    fonts.register("NotoSans-Regular.ttf", "Noto Sans");
+    fonts.register_emoji_font("Twemoji.Mozilla.ttf", "Twemoji");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(40.0);
+    text_style.set_font_families(&vec!["Noto Sans"]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    // Bare U+2764 like in twemoji_measuring(), then with VS16 and VS15.
+    text.add_span(
+        "❤ ❤\u{FE0F} ❤\u{FE0E} 1\u{FE0F}\u{20E3} # © ☺ 👍🏽 🇬🇧 ❤\u{FE0F}\u{200D}🔥\n",
+        text_style.clone(),
+    );
+    text.add_span_with_presentation("❤ ☺ ©\n", text_style.clone(), Presentation::Emoji);
+    text.add_span_with_presentation("❤\u{FE0F} 😀 👍🏽\n", text_style, Presentation::Text);
+
+    for report in emoji::presentation_report(&text, &fonts, "Twemoji") {
+        println!(
+            "{:?}: requested {:?}, resolved {:?}, font: {}, colour font: {}, rendered in colour: {}",
+            report.text,
+            report.requested,
+            report.resolved,
+            report.font_family.as_deref().unwrap_or("system fallback"),
+            report.colour_font,
+            report.rendered_in_colour
+        );
+    }
+
+    let paragraph = text.layout(&fonts, 1000.0);
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(1000, 240)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+    save_png(&mut surface, "output/emoji_presentation.png");
+}
+
+fn colour_font_matrix() {
+    let emoji_fonts = vec![
+        ("Twemoji.Mozilla.ttf", "Twemoji Mozilla"),
+        ("Twemoji-14.0.2.ttf", "Twemoji 14"),
+        ("seguiemj.ttf", "Segoe UI Emoji"),
+    ];
+    let mut fonts = FontEnvironment::new();
+    for (file, family) in emoji_fonts.iter() {
+        fonts.register(file, family);
+    }
+
+    let row_height = colour_fonts::matrix_height() + 20.0;
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(
+        colour_fonts::matrix_width() as i32,
+        (row_height * emoji_fonts.len() as f32) as i32,
+    ))
+    .unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    for (row, (file, family)) in emoji_fonts.iter().enumerate() {
+        let formats = colour_fonts::colour_formats(fonts.typeface(family).unwrap());
+        println!("{}: {:?}", file, formats);
+        let results =
+            colour_fonts::render_matrix(&fonts, family, surface.canvas(), row as f32 * row_height);
+        for result in results
+            .iter()
+            .filter(|result| result.outcome != colour_fonts::GlyphOutcome::Colour)
+        {
+            println!(
+                "  {:?} at {}px: {:?}",
+                result.sample, result.font_size, result.outcome
+            );
+        }
+    }
+    save_png(&mut surface, "output/colour_font_matrix.png");
+}
+
+fn automatic_emoji_routing() {
+    // Like box_character_github_friendly(), but 😁 comes from the designated emoji font instead
+    // of system fallback, and the primary family doesn't mention the emoji font at all.
+    let mut fonts = FontEnvironment::new().with_system_fallback();
+    fonts.register("NotoSans-Regular.ttf", "Noto Sans");
+    fonts.register_emoji_font("Twemoji.Mozilla.ttf", "Twemoji");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(48.0);
+    text_style.set_font_families(&vec!["Noto Sans"]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    // Digits, #, * and © have emoji glyphs in Twemoji, but default to text presentation.
+    text.add_span("Test 0123 # * © ⚀ 😁\n", text_style.clone());
+    text.add_span(
+        "1\u{FE0F}\u{20E3} #\u{FE0F}\u{20E3} ©\u{FE0F}\n",
+        text_style,
+    );
+
+    let emoji_family = fonts.emoji_family().unwrap().to_string();
+    for report in emoji::presentation_report(&text, &fonts, &emoji_family) {
+        println!(
+            "{:?}: {:?} from {}",
+            report.text,
+            report.resolved,
+            report.font_family.as_deref().unwrap_or("system fallback")
+        );
+    }
+
+    let paragraph = text.layout(&fonts, 1024.0);
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(1024, 200)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+    save_png(&mut surface, "output/automatic_emoji_routing.png");
+}
+
+fn text_decorations() {
+    let mut fonts = FontEnvironment::new();
+    let open_sans = fonts.register("OpenSans-Regular.ttf", "OpenSans");
+    fonts.register("Jua.woff2", "Jua");
+
+    let metrics = DecorationMetrics::read(&open_sans, 32.0);
+    println!(
+        "post underline: {:?} / {:?}, skia underline: {:?} / {:?}",
+        metrics.post_underline_position,
+        metrics.post_underline_thickness,
+        metrics.skia_underline_position,
+        metrics.skia_underline_thickness
+    );
+    println!(
+        "OS/2 strikeout: {:?} / {:?}, skia strikeout: {:?} / {:?}",
+        metrics.os2_strikeout_position,
+        metrics.os2_strikeout_thickness,
+        metrics.skia_strikeout_position,
+        metrics.skia_strikeout_thickness
+    );
+
+    let styles = vec![
+        TextDecorationStyle::Solid,
+        TextDecorationStyle::Double,
+        TextDecorationStyle::Dotted,
+        TextDecorationStyle::Dashed,
+        TextDecorationStyle::Wavy,
+    ];
+    let lines = vec![
+        TextDecoration::UNDERLINE,
+        TextDecoration::OVERLINE,
+        TextDecoration::LINE_THROUGH,
+    ];
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(1000, 1500)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    let mut top = 0.0;
+    for decoration_style in styles.iter() {
+        for decoration_lines in lines.iter() {
+            let decoration = Decoration {
+                lines: *decoration_lines,
+                style: *decoration_style,
+                color: Color::from_rgb(200, 0, 0),
+                thickness_multiplier: 1.5,
+                ..Decoration::default()
+            };
+            let mut text_style = TextStyle::new();
+            text_style.set_color(Color::from_rgb(0, 0, 0));
+            text_style.set_font_size(32.0);
+            text_style.set_font_families(&vec!["OpenSans"]);
+            decoration.apply(&mut text_style);
+            // Mixed sizes and fonts on one line, and trailing spaces in a bold run like in
+            // spaces_with_different_style(): the decoration should run on without steps or gaps.
+            let mut small_style = text_style.clone();
+            small_style.set_font_size(18.0);
+            let mut other_font_style = text_style.clone();
+            other_font_style.set_font_families(&vec!["Jua"]);
+            other_font_style.set_font_size(40.0);
+            let mut bold_style = text_style.clone();
+            bold_style.set_font_style(FontStyle::bold());
+
+            let mut style = ParagraphStyle::new();
+            style.set_text_style(&text_style);
+            let mut text = StyledText::new(style);
+            text.add_span("Lorem ipsum ", text_style);
+            text.add_span("dolor ", small_style);
+            text.add_span("sit amet ", other_font_style);
+            text.add_span("   \n", bold_style);
+            let paragraph = text.layout(&fonts, 1000.0);
+            paragraph.paint(surface.canvas(), skia_safe::Point::new(10.0, top));
+            top += paragraph.height() + 20.0;
+        }
+    }
+    save_png(&mut surface, "output/text_decorations.png");
+}
+
+fn text_effects() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("Adlery.woff2", "Adlery");
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(64.0);
+    text_style.set_font_families(&vec!["Adlery", "OpenSans"]);
+    let stroke = |width: f32, align: StrokeAlign, join: skia_safe::paint::Join| Stroke {
+        width,
+        colour: Color::from_rgb(0, 136, 0),
+        align,
+        join,
+        cap: skia_safe::paint::Cap::Round,
+        miter_limit: 4.0,
+    };
+    let gradient = skia_safe::Shader::linear_gradient(
+        ((0.0, 0.0), (600.0, 0.0)),
+        &[Color::from_rgb(255, 0, 128), Color::from_rgb(0, 128, 255)][..],
+        None,
+        skia_safe::TileMode::Clamp,
+        None,
+        None,
+    )
+    .unwrap();
+    let mut pattern_surface = Surface::new_raster_n32_premul(ISize::new(16, 16)).unwrap();
+    draw_backdrop(
+        pattern_surface.canvas(),
+        ISize::new(16, 16),
+        &Backdrop::Checkerboard(4),
+    );
+    let pattern = pattern_surface
+        .image_snapshot()
+        .to_shader(
+            (skia_safe::TileMode::Repeat, skia_safe::TileMode::Repeat),
+            skia_safe::SamplingOptions::default(),
+            None,
+        )
+        .unwrap();
+    let mut background = Paint::default();
+    background.set_color(Color::from_rgb(255, 240, 160));
+
+    let effects = vec![
+        (
+            "stroke only",
+            TextEffect {
+                fill: Fill::None,
+                stroke: Some(stroke(
+                    2.0,
+                    StrokeAlign::Centre,
+                    skia_safe::paint::Join::Miter,
+                )),
+                background: None,
+                shadows: vec![],
+                glows: vec![],
+            },
+        ),
+        (
+            "fill + outside stroke",
+            TextEffect {
+                fill: Fill::Colour(Color::from_rgb(0, 0, 0)),
+                stroke: Some(stroke(
+                    4.0,
+                    StrokeAlign::Outside,
+                    skia_safe::paint::Join::Round,
+                )),
+                background: None,
+                shadows: vec![],
+                glows: vec![],
+            },
+        ),
+        (
+            "fill + inside stroke",
+            TextEffect {
+                fill: Fill::Colour(Color::from_rgb(0, 0, 0)),
+                stroke: Some(stroke(
+                    4.0,
+                    StrokeAlign::Inside,
+                    skia_safe::paint::Join::Bevel,
+                )),
+                background: None,
+                shadows: vec![],
+                glows: vec![],
+            },
+        ),
+        (
+            "gradient + centre stroke",
+            TextEffect {
+                fill: Fill::Shader(gradient),
+                stroke: Some(stroke(
+                    1.5,
+                    StrokeAlign::Centre,
+                    skia_safe::paint::Join::Miter,
+                )),
+                background: Some(background),
+                shadows: vec![],
+                glows: vec![],
+            },
+        ),
+        (
+            "image shader",
+            TextEffect {
+                fill: Fill::Shader(pattern),
+                stroke: None,
+                background: None,
+                shadows: vec![],
+                glows: vec![],
+            },
+        ),
+    ];
+
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(600, 500)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    let mut top = 0.0;
+    for (name, effect) in effects {
+        let mut style = ParagraphStyle::new();
+        style.set_text_style(&text_style);
+        let mut text = StyledText::new(style);
+        text.add_span("Skia! ", text_style.clone());
+        text.add_span_with_effect(format!("{}\n", name), text_style.clone(), effect);
+        paint_with_effects(
+            &text,
+            &fonts,
+            600.0,
+            surface.canvas(),
+            skia_safe::Point::new(0.0, top),
+        );
+        top += text.layout(&fonts, 600.0).height();
+    }
+    save_png(&mut surface, "output/text_effects.png");
+}
+
+fn text_shadows() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(48.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let shadow = |x: f32, y: f32, blur_radius: f32, colour: Color, spread: f32| Shadow {
+        offset: skia_safe::Vector::new(x, y),
+        blur_radius,
+        colour,
+        spread,
+    };
+    let no_effect = TextEffect {
+        fill: Fill::Colour(Color::from_rgb(0, 0, 0)),
+        stroke: None,
+        background: None,
+        shadows: vec![],
+        glows: vec![],
+    };
+    let neon = Color::from_rgb(0, 255, 220);
+
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span_with_effect(
+        "Drop shadow\n",
+        text_style.clone(),
+        TextEffect {
+            shadows: vec![shadow(4.0, 4.0, 3.0, Color::from_argb(128, 0, 0, 0), 0.0)],
+            ..no_effect.clone()
+        },
+    );
+    text.add_span_with_effect(
+        "Layered shadows\n",
+        text_style.clone(),
+        TextEffect {
+            shadows: vec![
+                shadow(2.0, 2.0, 0.0, Color::from_rgb(255, 0, 0), 0.0),
+                shadow(4.0, 4.0, 0.0, Color::from_rgb(0, 160, 0), 0.0),
+                shadow(6.0, 6.0, 0.0, Color::from_rgb(0, 0, 255), 0.0),
+            ],
+            ..no_effect.clone()
+        },
+    );
+    text.add_span_with_effect(
+        "Spread shadow\n",
+        text_style.clone(),
+        TextEffect {
+            shadows: vec![shadow(0.0, 6.0, 8.0, Color::from_argb(160, 0, 0, 0), 3.0)],
+            ..no_effect.clone()
+        },
+    );
+    text.add_span_with_effect(
+        "Neon",
+        text_style.clone(),
+        TextEffect {
+            fill: Fill::Colour(Color::WHITE),
+            stroke: Some(Stroke {
+                width: 1.0,
+                colour: neon,
+                align: StrokeAlign::Outside,
+                join: skia_safe::paint::Join::Round,
+                cap: skia_safe::paint::Cap::Round,
+                miter_limit: 4.0,
+            }),
+            glows: vec![
+                Glow {
+                    colour: neon,
+                    radius: 24.0,
+                    spread: 4.0,
+                },
+                Glow {
+                    colour: neon,
+                    radius: 8.0,
+                    spread: 1.0,
+                },
+            ],
+            ..no_effect.clone()
+        },
+    );
+
+    // Size the canvas to the ink bounds rather than the paragraph, which would clip the
+    // shadows of the first line and the glow of the last.
+    let bounds = ink_bounds(&text, &fonts, 600.0).round_out();
+    let mut surface =
+        Surface::new_raster_n32_premul(ISize::new(bounds.width(), bounds.height())).unwrap();
+    surface.canvas().clear(Color::from_rgb(32, 32, 48));
+    paint_with_effects(
+        &text,
+        &fonts,
+        600.0,
+        surface.canvas(),
+        skia_safe::Point::new(-bounds.left as f32, -bounds.top as f32),
+    );
+    println!("Ink bounds: {:?}", bounds);
+    save_png(&mut surface, "output/text_shadows.png");
+}
+
+fn text_on_path() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+    fonts.register("Adlery.woff2", "Adlery");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(28.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut accent_style = text_style.clone();
+    accent_style.set_color(Color::from_rgb(0, 96, 200));
+    accent_style.set_font_families(&vec!["Adlery", "OpenSans"]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span("Text along a ", text_style.clone());
+    text.add_span("Skia path", accent_style);
+    text.add_span(" with a start offset", text_style.clone());
+
+    let mut wave = skia_safe::Path::new();
+    wave.move_to((40.0, 160.0));
+    wave.cubic_to((200.0, 20.0), (400.0, 300.0), (560.0, 120.0));
+    let circle = skia_safe::Path::circle((300.0, 420.0), 110.0, None);
+
+    let cases = vec![
+        (
+            "wave, start",
+            &wave,
+            PathTextOptions {
+                start_offset: 20.0,
+                ..PathTextOptions::default()
+            },
+        ),
+        (
+            "wave, centre, right side",
+            &wave,
+            PathTextOptions {
+                start_offset: 300.0,
+                align: PathAlign::Centre,
+                side: PathSide::Right,
+                ..PathTextOptions::default()
+            },
+        ),
+        (
+            "circle, wrap",
+            &circle,
+            PathTextOptions {
+                start_offset: 500.0,
+                overflow: PathOverflow::Wrap,
+                baseline_offset: 6.0,
+                ..PathTextOptions::default()
+            },
+        ),
+        (
+            "circle, shrink",
+            &circle,
+            PathTextOptions {
+                align: PathAlign::Centre,
+                side: PathSide::Right,
+                overflow: PathOverflow::Shrink,
+                ..PathTextOptions::default()
+            },
+        ),
+    ];
+
+    for (index, (name, path, options)) in cases.into_iter().enumerate() {
+        let path_text = layout_on_path(&text, &fonts, path, &options);
+        println!(
+            "{}: path length {:.1}, text advance {:.1}, scale {:.2}, placed {}, hidden {}, missing clusters {}",
+            name,
+            path_text.path_length,
+            path_text.text_advance,
+            path_text.scale,
+            path_text.glyphs_placed,
+            path_text.glyphs_hidden,
+            path_text.clusters_missing
+        );
+        let mut surface = Surface::new_raster_n32_premul(ISize::new(600, 560)).unwrap();
+        surface.canvas().clear(Color::from_rgb(255, 255, 255));
+        surface.canvas().draw_path(path, &path_guide_paint());
+        path_text.paint(surface.canvas());
+        save_png(&mut surface, &format!("output/text_on_path_{}.png", index));
+    }
+}
+
+fn warp_effects() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(48.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut accent_style = text_style.clone();
+    accent_style.set_color(Color::from_rgb(200, 0, 80));
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span("Warped ", text_style.clone());
+    text.add_span("Skia", accent_style);
+    text.add_span(" text", text_style.clone());
+
+    let cases = vec![
+        ("arc", Warp::Arc { bend: 0.5 }, WarpMode::Outline),
+        ("arc_rigid", Warp::Arc { bend: 0.5 }, WarpMode::RigidGlyphs),
+        ("arc_down", Warp::Arc { bend: -0.3 }, WarpMode::Outline),
+        (
+            "wave",
+            Warp::Wave {
+                amplitude: 12.0,
+                cycles: 1.5,
+            },
+            WarpMode::Outline,
+        ),
+        ("bulge", Warp::Bulge { amount: 0.6 }, WarpMode::Outline),
+        ("pinch", Warp::Bulge { amount: -0.4 }, WarpMode::Outline),
+        (
+            "flag",
+            Warp::Flag {
+                amplitude: 16.0,
+                cycles: 1.0,
+            },
+            WarpMode::Outline,
+        ),
+        (
+            "flag_rigid",
+            Warp::Flag {
+                amplitude: 16.0,
+                cycles: 1.0,
+            },
+            WarpMode::RigidGlyphs,
+        ),
+    ];
+    for (name, warp, mode) in cases {
+        let warped = warp_text(&text, &fonts, warp, mode);
+        let bounds = warped.bounds.round_out();
+        println!(
+            "{}: bounds {:?}, missing clusters {}",
+            name, bounds, warped.clusters_missing
+        );
+        let margin = 8;
+        let mut surface = Surface::new_raster_n32_premul(ISize::new(
+            bounds.width() + 2 * margin,
+            bounds.height() + 2 * margin,
+        ))
+        .unwrap();
+        let canvas = surface.canvas();
+        canvas.clear(Color::from_rgb(255, 255, 255));
+        canvas.translate(((margin - bounds.left) as f32, (margin - bounds.top) as f32));
+        warped.paint(canvas);
+        save_png(&mut surface, &format!("output/warp_{}.png", name));
+        warped.write_svg(&format!("output/warp_{}.svg", name));
+    }
+}
+
+fn text_animation() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(32.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut accent_style = text_style.clone();
+    accent_style.set_color(Color::from_rgb(0, 96, 200));
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span("Every frame reuses ", text_style.clone());
+    text.add_span("one layout", accent_style);
+    text.add_span(
+        ", so the text never reflows while it animates.",
+        text_style.clone(),
+    );
+
+    let animations = vec![
+        ("typewriter", Animation::typewriter(48)),
+        ("fade_in_by_word", Animation::fade_in_by_word(30)),
+        ("slide_up", Animation::slide_up(30, 40.0)),
+        ("zoom_in", Animation::zoom_in(20, 12.0)),
+    ];
+    for (name, animation) in animations.iter() {
+        let report = render_frames(
+            &text,
+            &fonts,
+            400.0,
+            ISize::new(420, 200),
+            animation,
+            &format!("output/animation_{}", name),
+        );
+        let report = match report {
+            Ok(report) => report,
+            Err(reason) => {
+                println!("{}: {}", name, reason);
+                continue;
+            }
+        };
+        println!(
+            "{}: {} frames, {} glyphs in {} units, {} missing clusters, final frame differs in {} pixels (max {}), drifting frames {:?}",
+            name,
+            report.frames_written,
+            report.glyph_count,
+            report.unit_count,
+            report.clusters_missing,
+            report.final_frame.differing_pixels,
+            report.final_frame.max_channel_difference,
+            report.drifting_frames
+        );
+    }
+}
+
+fn inline_placeholders() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+    fonts.register_emoji_font("Twemoji.Mozilla.ttf", "Twemoji");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(24.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+
+    // A small icon image to paint into some of the boxes.
+    let mut icon_surface = Surface::new_raster_n32_premul(ISize::new(32, 32)).unwrap();
+    icon_surface.canvas().clear(Color::from_rgb(0, 120, 215));
+    let mut icon_paint = Paint::default();
+    icon_paint.set_anti_alias(true);
+    icon_paint.set_color(Color::WHITE);
+    icon_surface
+        .canvas()
+        .draw_circle((16.0, 16.0), 10.0, &icon_paint);
+    let icon = icon_surface.image_snapshot();
+
+    let with_image = |mut placeholder: Placeholder| {
+        placeholder.image = Some(icon.clone());
+        placeholder
+    };
+    let mut baseline_box = Placeholder::new(30.0, 30.0, PlaceholderAlignment::Baseline);
+    baseline_box.baseline_offset = 24.0;
+
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span("Baseline ", text_style.clone());
+    text.add_placeholder(baseline_box, text_style.clone());
+    text.add_span(" next to 😁 emoji, ", text_style.clone());
+    text.add_span("middle ", text_style.clone());
+    text.add_placeholder(
+        with_image(Placeholder::new(24.0, 24.0, PlaceholderAlignment::Middle)),
+        text_style.clone(),
+    );
+    text.add_span(" top ", text_style.clone());
+    text.add_placeholder(
+        Placeholder::new(20.0, 40.0, PlaceholderAlignment::Top),
+        text_style.clone(),
+    );
+    text.add_span(" bottom ", text_style.clone());
+    text.add_placeholder(
+        Placeholder::new(20.0, 40.0, PlaceholderAlignment::Bottom),
+        text_style.clone(),
+    );
+    text.add_span(" and a tall one ", text_style.clone());
+    text.add_placeholder(
+        with_image(Placeholder::new(
+            48.0,
+            64.0,
+            PlaceholderAlignment::AboveBaseline,
+        )),
+        text_style.clone(),
+    );
+    text.add_span(" that makes its line taller 👍🏽.", text_style.clone());
+
+    let width = 300.0;
+    let paragraph = text.layout(&fonts, width);
+    for line in paragraph.get_line_metrics().iter() {
+        println!(
+            "line {}: {}..{}, height {:.2}, ascent {:.2}, descent {:.2}, baseline {:.2}",
+            line.line_number,
+            line.start_index,
+            line.end_index,
+            line.height,
+            line.ascent,
+            line.descent,
+            line.baseline
+        );
+    }
+    for (placeholder, rect) in placeholder_rects(&text, &paragraph) {
+        println!(
+            "{:?} placeholder {}x{}: {:?}",
+            placeholder.alignment, placeholder.width, placeholder.height, rect
+        );
+    }
+
+    let mut surface =
+        Surface::new_raster_n32_premul(ISize::new(width as i32, paragraph.height().ceil() as i32))
+            .unwrap();
+    let canvas = surface.canvas();
+    canvas.clear(Color::WHITE);
+    paragraph.paint(canvas, skia_safe::Point::new(0.0, 0.0));
+    paint_placeholders(&text, &paragraph, canvas, skia_safe::Point::new(0.0, 0.0));
+    save_png(&mut surface, "output/inline_placeholders.png");
+}
+
+fn text_flow() {
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(14.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut heading_style = text_style.clone();
+    heading_style.set_font_size(22.0);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    let body = "Skia lays a paragraph out into a single width, but documents often need the same \
+        text to continue from one frame into the next. Each box takes the lines that fit its \
+        height, and the following box picks up where it stopped. ";
+    for section in 1..=3 {
+        text.add_span(format!("Section {}\n", section), heading_style.clone());
+        text.add_span(format!("{}{}\n", body, body), text_style.clone());
+    }
+
+    let page = skia_safe::Rect::from_xywh(10.0, 10.0, 580.0, 300.0);
+    let linked_frames = vec![
+        skia_safe::Rect::from_xywh(10.0, 330.0, 200.0, 120.0),
+        skia_safe::Rect::from_xywh(230.0, 330.0, 360.0, 60.0),
+        skia_safe::Rect::from_xywh(230.0, 400.0, 360.0, 10.0),
+        skia_safe::Rect::from_xywh(10.0, 470.0, 580.0, 120.0),
+    ];
+    let layouts = vec![
+        ("columns", columns(page, 3, 16.0)),
+        ("linked_frames", linked_frames),
+    ];
+    for (name, boxes) in layouts {
+        let flowed = flow(&text, &fonts, &boxes);
+        for (index, flowed_box) in flowed.boxes.iter().enumerate() {
+            println!(
+                "{} box {}: {}..{} in {} lines",
+                name, index, flowed_box.start, flowed_box.end, flowed_box.line_count
+            );
+        }
+        println!("{} overflow: {:?}", name, flowed.overflow);
+
+        let mut surface = Surface::new_raster_n32_premul(ISize::new(600, 600)).unwrap();
+        let canvas = surface.canvas();
+        canvas.clear(Color::WHITE);
+        let mut frame_paint = Paint::default();
+        frame_paint.set_style(Style::Stroke);
+        frame_paint.set_color(Color::from_rgb(200, 200, 200));
+        for rect in boxes.iter() {
+            canvas.draw_rect(rect, &frame_paint);
+        }
+        flowed.paint(canvas);
+        save_png(&mut surface, &format!("output/text_flow_{}.png", name));
+    }
+}
+
+fn hyphenated_narrow_box() {
+    // The 242.48px box and style of multi_line_end_spaces(), with longer words.
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+    let mut hyphenator = Hyphenator::new(HyphenationSettings::default());
+    if let Err(error) = hyphenator.load_locale("en-US", "hyph-en-us.pat.txt") {
+        println!(
+            "Can't load hyph-en-us.pat.txt ({}), using hyph-en-us-sample.pat.txt",
+            error
+        );
+        if let Err(error) = hyphenator.load_locale("en-US", "hyph-en-us-sample.pat.txt") {
+            println!("Can't load hyph-en-us-sample.pat.txt ({})", error);
+        }
+    }
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(18.666666666666668);
+    text_style.set_letter_spacing(1.8666666666666668);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    text_style.set_locale("en-US");
+    let mut unknown_locale_style = text_style.clone();
+    unknown_locale_style.set_locale("xx");
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    let mut emphasis_style = text_style.clone();
+    emphasis_style.set_color(Color::from_rgb(200, 0, 0));
+    // "typographical" runs across two spans and is still hyphenated as one word.
+    text.add_span(
+        "Internationalization considerations complicate typo",
+        text_style.clone(),
+    );
+    text.add_span("graphical", emphasis_style);
+    text.add_span(" hyphenation. ", text_style.clone());
+    text.add_span(
+        "Spans without patterns for their locale keep unbreakable words.",
+        unknown_locale_style,
+    );
+
+    let width = 242.48;
+    let plain = text.layout(&fonts, width);
+    let hyphenated = hyphenator.layout(&text, &fonts, width);
+    println!(
+        "{} lines without hyphenation, {} with {} hyphens ({} dropped)",
+        plain.get_line_metrics().len(),
+        hyphenated.paragraph.get_line_metrics().len(),
+        hyphenated.hyphens,
+        hyphenated.dropped
+    );
+    println!("{:?}", hyphenated.text.text());
+
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(520, 300)).unwrap();
+    let canvas = surface.canvas();
+    canvas.clear(Color::WHITE);
+    let mut box_paint = Paint::default();
+    box_paint.set_style(Style::Stroke);
+    box_paint.set_color(Color::from_rgb(255, 0, 0));
+    canvas.draw_rect(
+        skia_safe::Rect::from_xywh(0.0, 0.0, width, 300.0),
+        &box_paint,
+    );
+    canvas.draw_rect(
+        skia_safe::Rect::from_xywh(260.0, 0.0, width, 300.0),
+        &box_paint,
+    );
+    plain.paint(canvas, skia_safe::Point::new(0.0, 0.0));
+    hyphenated
+        .paragraph
+        .paint(canvas, skia_safe::Point::new(260.0, 0.0));
+    save_png(&mut surface, "output/hyphenated_narrow_box.png");
+}
+
+fn line_break_policy() {
+    // The punctuation cases of text_wrapping() at the same width, with a URL and a word longer
+    // than the line, laid out as is and with the default policy.
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(32.0);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span("Lorem ipsum/dolor\nLorem ipsum?dolor\nLorem ipsum,dolor\nLorem ipsum.dolor\nLorem ipsum<dolor\nLorem ipsum>dolor\n", text_style.clone());
+    text.add_span(
+        "See https://skia.org/docs/user/modules/skparagraph for details.\n",
+        text_style.clone(),
+    );
+    text.add_span(
+        "Pneumonoultramicroscopicsilicovolcanoconiosis\n",
+        text_style.clone(),
+    );
+
+    let width = 230.0;
+    let policy = LineBreakPolicy::default();
+    let hinted = policy.apply(&text, &fonts, width);
+    println!(
+        "text without hints unchanged: {}",
+        strip_break_hints(&hinted.text()) == text.text()
+    );
+
+    let mut surface = Surface::new_raster_n32_premul(ISize::new(500, 1000)).unwrap();
+    let canvas = surface.canvas();
+    canvas.clear(Color::WHITE);
+    let mut box_paint = Paint::default();
+    box_paint.set_style(Style::Stroke);
+    box_paint.set_color(Color::from_rgb(255, 0, 0));
+    for (column, text) in [&text, &hinted].iter().enumerate() {
+        let left = column as f32 * 250.0;
+        let paragraph = text.layout(&fonts, width);
+        println!(
+            "column {}: {} lines",
+            column,
+            paragraph.get_line_metrics().len()
+        );
+        canvas.draw_rect(
+            skia_safe::Rect::from_xywh(left, 0.0, width, 1000.0),
+            &box_paint,
+        );
+        paragraph.paint(canvas, skia_safe::Point::new(left, 0.0));
+    }
+    save_png(&mut surface, "output/line_break_policy.png");
+}
+
+fn trailing_whitespace_modes() {
+    // The texts of multi_line_end_spaces() and spaces_with_different_style(), measured per line
+    // and right-aligned with each way of counting the trailing spaces.
+    let mut fonts = FontEnvironment::new();
+    fonts.register("OpenSans-Regular.ttf", "OpenSans");
+
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(18.666666666666668);
+    text_style.set_letter_spacing(1.8666666666666668);
+    text_style.set_font_families(&vec!["OpenSans"]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut end_spaces = StyledText::new(style.clone());
+    end_spaces.add_span("Two lines with spaces  at the ends \n", text_style.clone());
+
+    let mut plain_style = text_style.clone();
+    plain_style.set_letter_spacing(0.0);
+    let mut bold_style = plain_style.clone();
+    bold_style.set_font_style(FontStyle::bold());
+    let mut styled_spaces = StyledText::new(style);
+    styled_spaces.add_span("Lorem ipsum ", plain_style);
+    styled_spaces.add_span("   \n", bold_style);
+
+    let modes = [
+        TrailingWhitespace::Exclude,
+        TrailingWhitespace::Include,
+        TrailingWhitespace::IncludeUpToLayoutWidth,
+    ];
+    for (name, text, width) in vec![
+        ("end_spaces", &end_spaces, 242.48),
+        ("styled_spaces", &styled_spaces, 150.0),
+    ] {
+        let paragraph = text.layout(&fonts, width);
+        let lines = measure_trailing_whitespace(&paragraph, &text.text(), width);
+        for line in lines.iter() {
+            println!(
+                "{} line {}: whitespace {:?}, width {:.2} without, {:.2} with, {} rects, hangs: {}",
+                name,
+                line.line_number,
+                line.range,
+                line.width_without_whitespace(),
+                line.width_with_whitespace(),
+                line.whitespace_rects.len(),
+                line.hangs
+            );
+        }
+
+        let band_height = paragraph.height() + 10.0;
+        let mut surface = Surface::new_raster_n32_premul(ISize::new(
+            width as i32 + 80,
+            (band_height * 3.0) as i32,
+        ))
+        .unwrap();
+        let canvas = surface.canvas();
+        canvas.clear(Color::WHITE);
+        let mut box_paint = Paint::default();
+        box_paint.set_style(Style::Stroke);
+        for (index, mode) in modes.iter().enumerate() {
+            let origin = skia_safe::Point::new(10.0, index as f32 * band_height);
+            println!(
+                "{} {:?}: bounding width {:.2}",
+                name,
+                mode,
+                bounding_width(&lines, *mode, width)
+            );
+            box_paint.set_color(Color::from_rgb(255, 0, 0));
+            canvas.draw_rect(
+                skia_safe::Rect::from_xywh(origin.x, origin.y, width, paragraph.height()),
+                &box_paint,
+            );
+            paint_aligned(
+                &paragraph,
+                &lines,
+                *mode,
+                TextAlign::Right,
+                width,
+                canvas,
+                origin,
+            );
+        }
+        save_png(
+            &mut surface,
+            &format!("output/trailing_whitespace_{}.png", name),
+        );
+    }
 }
 
 fn remove_unsupported_font_features(text_style: &mut TextStyle) {
@@ -332,6 +1835,21 @@ fn devanagari_test() {
     save_png(&mut surface, "output/devanagari_test.png");
 }
 
+fn font_metrics_comparison() {
+    let font_files = vec![
+        "LeagueSpartan.woff2",
+        "Jua.woff2",
+        "OpenSans-Regular.ttf",
+        "NotoSans-Regular.ttf",
+        "Adigiana_Ultra.ttf",
+        "Montalaq.ttf",
+        "Twemoji.Mozilla.ttf",
+    ];
+    for file in font_files {
+        font_metrics::print_metrics_report(file, 100.0);
+    }
+}
+
 fn get_ascent_from_font() {
     let typeface =
         Typeface::from_data(data_from_file_path(Path::new("LeagueSpartan.woff2")), None).unwrap();
@@ -793,32 +2311,73 @@ pub fn data_from_file_path(file_path: &Path) -> Data {
 }
 
 pub fn save_png(surface: &mut Surface, path: &str) -> bool {
-    let mut bytes: Vec<u8> = vec![];
+    save_png_over(surface, path, Backdrop::Transparent)
+}
+
+// What to put behind the rendered pixels when encoding a PNG. Text layers are rendered onto a
+// transparent canvas; the other options are only meant for previews.
+pub enum Backdrop {
+    Transparent,
+    Solid(Color),
+    // Grey checkerboard with the given cell size in pixels.
+    Checkerboard(i32),
+}
+
+pub fn save_png_over(surface: &mut Surface, path: &str, backdrop: Backdrop) -> bool {
     let image_info = surface.image_info();
+    let mut composited = match backdrop {
+        Backdrop::Transparent => None,
+        backdrop => {
+            let mut preview = Surface::new_raster_n32_premul(image_info.dimensions()).unwrap();
+            draw_backdrop(preview.canvas(), image_info.dimensions(), &backdrop);
+            // The snapshot is premultiplied, so drawing it with the default SrcOver paint
+            // composites it correctly.
+            preview
+                .canvas()
+                .draw_image(surface.image_snapshot(), (0.0, 0.0), None);
+            Some(preview)
+        }
+    };
+    let surface = composited.as_mut().unwrap_or(surface);
+
+    match read_rgba_pixels(surface) {
+        Some(pixels) => save_rgba_png(&pixels, image_info.width(), image_info.height(), path),
+        None => {
+            println!("failed to read pixels");
+            false
+        }
+    }
+}
+
+// The surface is N32 premultiplied, which is BGRA on most platforms. PNG expects straight
+// (unpremultiplied) RGBA, so let skia do both conversions while reading the pixels back.
+pub fn read_rgba_pixels(surface: &mut Surface) -> Option<Vec<u8>> {
+    let rgba_info = ImageInfo::new(
+        surface.image_info().dimensions(),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let mut pixels = vec![0; rgba_info.height() as usize * rgba_info.min_row_bytes()];
+    if surface.read_pixels(&rgba_info, &mut pixels, rgba_info.min_row_bytes(), (0, 0)) {
+        Some(pixels)
+    } else {
+        None
+    }
+}
+
+pub fn save_rgba_png(pixels: &[u8], width: i32, height: i32, path: &str) -> bool {
+    let mut bytes: Vec<u8> = vec![];
     {
-        let mut encoder = png::Encoder::new(
-            &mut bytes,
-            image_info.width() as u32,
-            image_info.height() as u32,
-        );
+        let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
         encoder.set_color(png::ColorType::RGBA);
         encoder.set_depth(png::BitDepth::Eight);
 
         let mut writer = encoder.write_header().expect("failed to write file header");
-
-        let mut dst_pixels = vec![0; image_info.height() as usize * image_info.min_row_bytes()];
-        let pixels_read = surface.read_pixels(
-            &image_info,
-            &mut dst_pixels,
-            image_info.min_row_bytes(),
-            (0, 0),
-        );
-        if !pixels_read {
-            println!("failed to read pixels");
-        }
-        let result = writer.write_image_data(dst_pixels.as_slice());
+        let result = writer.write_image_data(pixels);
         if let Err(reason) = result {
             println!("failed to write image data: {}", reason);
+            return false;
         }
     }
     let data = skia_safe::Data::new_copy(&bytes);
@@ -829,3 +2388,35 @@ pub fn save_png(surface: &mut Surface, path: &str) -> bool {
 
     return true;
 }
+
+fn draw_backdrop(canvas: &mut Canvas, size: ISize, backdrop: &Backdrop) {
+    match backdrop {
+        Backdrop::Transparent => {
+            canvas.clear(Color::TRANSPARENT);
+        }
+        Backdrop::Solid(color) => {
+            canvas.clear(*color);
+        }
+        Backdrop::Checkerboard(cell_size) => {
+            canvas.clear(Color::WHITE);
+            let mut paint = Paint::default();
+            paint.set_color(Color::from_rgb(204, 204, 204));
+            let cell_size = (*cell_size).max(1);
+            for row in 0..(size.height + cell_size - 1) / cell_size {
+                for column in 0..(size.width + cell_size - 1) / cell_size {
+                    if (row + column) % 2 == 1 {
+                        canvas.draw_irect(
+                            IRect::from_xywh(
+                                column * cell_size,
+                                row * cell_size,
+                                cell_size,
+                                cell_size,
+                            ),
+                            &paint,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::styled_text::StyledText;
+use skia_safe::paint::Style;
+use skia_safe::textlayout::{Paragraph, PlaceholderAlignment, PlaceholderStyle, TextBaseline};
+use skia_safe::{Canvas, Color, Image, Paint, Point, Rect};
+
+// SkParagraph stands an object replacement character in for every placeholder.
+pub const OBJECT_REPLACEMENT: char = '\u{FFFC}';
+
+// An inline box for an image or icon, laid out like a character of the given size.
+#[derive(Clone)]
+pub struct Placeholder {
+    pub width: f32,
+    pub height: f32,
+    pub alignment: PlaceholderAlignment,
+    // The baseline `baseline_offset` is measured against, for the Baseline alignment.
+    pub baseline: TextBaseline,
+    // Distance from the top of the box to the baseline, for the Baseline alignment.
+    pub baseline_offset: f32,
+    // Painted into the box by `paint_placeholders()`. Without an image a debug box is painted.
+    pub image: Option<Image>,
+}
+
+impl Placeholder {
+    pub fn new(width: f32, height: f32, alignment: PlaceholderAlignment) -> Self {
+        Placeholder {
+            width,
+            height,
+            alignment,
+            baseline: TextBaseline::Alphabetic,
+            baseline_offset: height,
+            image: None,
+        }
+    }
+
+    pub fn style(&self) -> PlaceholderStyle {
+        PlaceholderStyle::new(
+            self.width,
+            self.height,
+            self.alignment,
+            self.baseline,
+            self.baseline_offset,
+        )
+    }
+}
+
+// The rect every placeholder of `text` got in `paragraph`, in paragraph coordinates and in the
+// order of the spans.
+pub fn placeholder_rects<'a>(
+    text: &'a StyledText,
+    paragraph: &Paragraph,
+) -> Vec<(&'a Placeholder, Rect)> {
+    let placeholders = text
+        .spans
+        .iter()
+        .filter_map(|span| span.placeholder.as_ref());
+    placeholders
+        .zip(paragraph.get_rects_for_placeholders().iter())
+        .map(|(placeholder, text_box)| (placeholder, text_box.rect))
+        .collect()
+}
+
+// Paints each placeholder's image, scaled to its box, or a debug box showing the box, its
+// diagonals and the baseline it was aligned to.
+pub fn paint_placeholders(
+    text: &StyledText,
+    paragraph: &Paragraph,
+    canvas: &mut Canvas,
+    origin: Point,
+) {
+    for (placeholder, rect) in placeholder_rects(text, paragraph) {
+        let rect = rect.with_offset(origin);
+        match &placeholder.image {
+            Some(image) => {
+                let mut paint = Paint::default();
+                paint.set_anti_alias(true);
+                canvas.draw_image_rect(image, None, rect, &paint);
+            }
+            None => {
+                let mut paint = Paint::default();
+                paint.set_anti_alias(true);
+                paint.set_color(Color::from_argb(48, 255, 0, 255));
+                canvas.draw_rect(rect, &paint);
+                paint.set_color(Color::from_rgb(255, 0, 255));
+                paint.set_style(Style::Stroke);
+                canvas.draw_rect(rect, &paint);
+                canvas.draw_line((rect.left, rect.top), (rect.right, rect.bottom), &paint);
+                canvas.draw_line((rect.left, rect.bottom), (rect.right, rect.top), &paint);
+                if placeholder.alignment == PlaceholderAlignment::Baseline {
+                    let baseline = rect.top + placeholder.baseline_offset;
+                    paint.set_color(Color::from_rgb(0, 160, 0));
+                    canvas.draw_line(
+                        (rect.left - 4.0, baseline),
+                        (rect.right + 4.0, baseline),
+                        &paint,
+                    );
+                }
+            }
+        }
+    }
+}
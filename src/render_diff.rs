@@ -0,0 +1,64 @@
+pub struct RenderComparison {
+    pub differing_pixels: usize,
+    pub max_channel_difference: u8,
+}
+
+impl RenderComparison {
+    pub fn identical(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+// Compares two RGBA buffers. The diff image shows differing pixels in red over a faded copy of
+// `expected`.
+pub fn compare(expected: &[u8], actual: &[u8]) -> (RenderComparison, Vec<u8>) {
+    let mut comparison = RenderComparison {
+        differing_pixels: 0,
+        max_channel_difference: 0,
+    };
+    let mut diff = Vec::with_capacity(expected.len());
+    for (expected, actual) in expected.chunks(4).zip(actual.chunks(4)) {
+        let difference = expected
+            .iter()
+            .zip(actual.iter())
+            .map(|(a, b)| (*a as i16 - *b as i16).abs() as u8)
+            .max()
+            .unwrap_or(0);
+        if difference > 0 {
+            comparison.differing_pixels += 1;
+            comparison.max_channel_difference = comparison.max_channel_difference.max(difference);
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            diff.extend_from_slice(&[expected[0], expected[1], expected[2], expected[3] / 4]);
+        }
+    }
+    // Buffers of different sizes can't hold the same image. Pixels only one of them has count as
+    // differing.
+    let extra_pixels = (expected.len().max(actual.len()) - expected.len().min(actual.len())) / 4;
+    if extra_pixels > 0 {
+        comparison.differing_pixels += extra_pixels;
+        comparison.max_channel_difference = 255;
+    }
+    for _ in actual.len() / 4..expected.len() / 4 {
+        diff.extend_from_slice(&[255, 0, 0, 255]);
+    }
+    (comparison, diff)
+}
+
+// Whether any visible pixel of an RGBA buffer has a hue. Text painted in black or grey only
+// produces neutral pixels, so coloured pixels mean a colour glyph was drawn.
+pub fn has_colour(pixels: &[u8]) -> bool {
+    pixels.chunks(4).any(|pixel| {
+        let max = pixel[0].max(pixel[1]).max(pixel[2]);
+        let min = pixel[0].min(pixel[1]).min(pixel[2]);
+        pixel[3] > 0 && max - min > 16
+    })
+}
+
+// Whether any pixel of an RGBA buffer differs from the background, taken from the top-left pixel.
+pub fn has_ink(pixels: &[u8]) -> bool {
+    match pixels.get(0..4) {
+        Some(background) => pixels.chunks(4).any(|pixel| pixel != background),
+        None => false,
+    }
+}
@@ -0,0 +1,344 @@
+// Minimal big-endian readers for raw OpenType tables, as returned by Typeface::get_table_data().
+// Out-of-range reads return None instead of panicking, since a lot of fonts in the wild have
+// truncated or malformed tables.
+
+use skia_safe::Typeface;
+
+pub fn tag(name: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*name)
+}
+
+pub fn tag_name(tag: u32) -> String {
+    tag.to_be_bytes()
+        .iter()
+        .map(|byte| {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+pub fn table(typeface: &Typeface, name: &[u8; 4]) -> Option<Vec<u8>> {
+    table_by_tag(typeface, tag(name))
+}
+
+pub fn table_by_tag(typeface: &Typeface, tag: u32) -> Option<Vec<u8>> {
+    let size = typeface.get_table_size(tag)?;
+    if size == 0 {
+        return None;
+    }
+    let mut data = vec![0; size];
+    let copied = typeface.get_table_data(tag, &mut data);
+    data.truncate(copied);
+    Some(data)
+}
+
+pub fn u8_at(data: &[u8], offset: usize) -> Option<u8> {
+    data.get(offset).copied()
+}
+
+pub fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub fn i16_at(data: &[u8], offset: usize) -> Option<i16> {
+    u16_at(data, offset).map(|value| value as i16)
+}
+
+pub fn u24_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 3)?;
+    Some(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+}
+
+pub fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// 16.16 fixed point number.
+pub fn fixed_at(data: &[u8], offset: usize) -> Option<f32> {
+    u32_at(data, offset).map(|value| value as i32 as f32 / 65536.0)
+}
+
+// Reads the best Unicode subtable of a `cmap` table and returns (code point, glyph id) pairs
+// sorted by code point. Only formats 4 and 12 are supported; they cover practically every font.
+pub fn cmap_mappings(cmap: &[u8]) -> Vec<(u32, u16)> {
+    let subtable_count = u16_at(cmap, 2).unwrap_or(0) as usize;
+    let mut best: Option<(u8, usize)> = None;
+    for index in 0..subtable_count {
+        let record = 4 + index * 8;
+        let (platform, encoding, offset) = match (
+            u16_at(cmap, record),
+            u16_at(cmap, record + 2),
+            u32_at(cmap, record + 4),
+        ) {
+            (Some(platform), Some(encoding), Some(offset)) => (platform, encoding, offset as usize),
+            _ => break,
+        };
+        let format = u16_at(cmap, offset).unwrap_or(0);
+        let priority = match (platform, encoding, format) {
+            (3, 10, 12) | (0, 4, 12) | (0, 6, 12) => 4,
+            (0, _, 12) => 3,
+            (3, 1, 4) | (0, _, 4) => 2,
+            (3, 0, 4) => 1,
+            _ => 0,
+        };
+        if priority > 0
+            && best
+                .map(|(best_priority, _)| priority > best_priority)
+                .unwrap_or(true)
+        {
+            best = Some((priority, offset));
+        }
+    }
+    let offset = match best {
+        Some((_, offset)) => offset,
+        None => return vec![],
+    };
+    let subtable = &cmap[offset..];
+    let mut mappings = match u16_at(subtable, 0) {
+        Some(4) => cmap_format_4(subtable),
+        Some(12) => cmap_format_12(subtable),
+        _ => vec![],
+    };
+    mappings.sort_unstable();
+    mappings.dedup_by_key(|(code_point, _)| *code_point);
+    mappings
+}
+
+fn cmap_format_4(subtable: &[u8]) -> Vec<(u32, u16)> {
+    let mut mappings = vec![];
+    let segment_count = u16_at(subtable, 6).unwrap_or(0) as usize / 2;
+    let end_codes = 14;
+    let start_codes = end_codes + segment_count * 2 + 2;
+    let id_deltas = start_codes + segment_count * 2;
+    let id_range_offsets = id_deltas + segment_count * 2;
+    for segment in 0..segment_count {
+        let (end, start, delta, range_offset) = match (
+            u16_at(subtable, end_codes + segment * 2),
+            u16_at(subtable, start_codes + segment * 2),
+            u16_at(subtable, id_deltas + segment * 2),
+            u16_at(subtable, id_range_offsets + segment * 2),
+        ) {
+            (Some(end), Some(start), Some(delta), Some(range_offset)) => {
+                (end, start, delta, range_offset)
+            }
+            _ => break,
+        };
+        if start == 0xFFFF || start > end {
+            continue;
+        }
+        for code_point in start..=end {
+            let glyph = if range_offset == 0 {
+                code_point.wrapping_add(delta)
+            } else {
+                let glyph_offset = id_range_offsets
+                    + segment * 2
+                    + range_offset as usize
+                    + (code_point - start) as usize * 2;
+                match u16_at(subtable, glyph_offset) {
+                    Some(0) | None => 0,
+                    Some(glyph) => glyph.wrapping_add(delta),
+                }
+            };
+            if glyph != 0 {
+                mappings.push((code_point as u32, glyph));
+            }
+        }
+    }
+    mappings
+}
+
+fn cmap_format_12(subtable: &[u8]) -> Vec<(u32, u16)> {
+    let mut mappings = vec![];
+    let group_count = u32_at(subtable, 12).unwrap_or(0) as usize;
+    for group in 0..group_count {
+        let record = 16 + group * 12;
+        let (start, end, start_glyph) = match (
+            u32_at(subtable, record),
+            u32_at(subtable, record + 4),
+            u32_at(subtable, record + 8),
+        ) {
+            (Some(start), Some(end), Some(start_glyph)) => (start, end, start_glyph),
+            _ => break,
+        };
+        if start > end || end > 0x10FFFF {
+            continue;
+        }
+        for code_point in start..=end {
+            let glyph = start_glyph + (code_point - start);
+            if glyph != 0 && glyph <= 0xFFFF {
+                mappings.push((code_point, glyph as u16));
+            }
+        }
+    }
+    mappings
+}
+
+// Reads the format 14 subtable of a `cmap` table and returns (variation selector, base code
+// point, glyph id) triples sorted by selector and base. The glyph is `None` for sequences shown
+// with the base character's default glyph.
+pub fn cmap_variation_sequences(cmap: &[u8]) -> Vec<(u32, u32, Option<u16>)> {
+    let subtable_count = u16_at(cmap, 2).unwrap_or(0) as usize;
+    let subtable = (0..subtable_count)
+        .filter_map(|index| u32_at(cmap, 4 + index * 8 + 4))
+        .map(|offset| offset as usize)
+        .find(|offset| u16_at(cmap, *offset) == Some(14))
+        .and_then(|offset| cmap.get(offset..));
+    let subtable = match subtable {
+        Some(subtable) => subtable,
+        None => return vec![],
+    };
+    let mut sequences = vec![];
+    let record_count = u32_at(subtable, 6).unwrap_or(0) as usize;
+    for index in 0..record_count {
+        let record = 10 + index * 11;
+        let (selector, default_uvs, non_default_uvs) = match (
+            u24_at(subtable, record),
+            u32_at(subtable, record + 3),
+            u32_at(subtable, record + 7),
+        ) {
+            (Some(selector), Some(default_uvs), Some(non_default_uvs)) => {
+                (selector, default_uvs as usize, non_default_uvs as usize)
+            }
+            _ => break,
+        };
+        if default_uvs != 0 {
+            let range_count = u32_at(subtable, default_uvs).unwrap_or(0) as usize;
+            for range in 0..range_count {
+                let range = default_uvs + 4 + range * 4;
+                if let (Some(start), Some(additional)) =
+                    (u24_at(subtable, range), u8_at(subtable, range + 3))
+                {
+                    sequences.extend(
+                        (start..=start + additional as u32).map(|base| (selector, base, None)),
+                    );
+                }
+            }
+        }
+        if non_default_uvs != 0 {
+            let mapping_count = u32_at(subtable, non_default_uvs).unwrap_or(0) as usize;
+            for mapping in 0..mapping_count {
+                let mapping = non_default_uvs + 4 + mapping * 5;
+                if let (Some(base), Some(glyph)) =
+                    (u24_at(subtable, mapping), u16_at(subtable, mapping + 3))
+                {
+                    sequences.push((selector, base, Some(glyph)));
+                }
+            }
+        }
+    }
+    sequences.sort_unstable();
+    sequences.dedup_by_key(|(selector, base, _)| (*selector, *base));
+    sequences
+}
+
+// Script and feature tags listed in a GSUB or GPOS table.
+pub fn layout_scripts_and_features(table: &[u8]) -> (Vec<String>, Vec<String>) {
+    let tag_list = |list_offset: Option<u16>| -> Vec<String> {
+        let list_offset = match list_offset {
+            Some(offset) if offset != 0 => offset as usize,
+            _ => return vec![],
+        };
+        let count = u16_at(table, list_offset).unwrap_or(0) as usize;
+        let mut tags: Vec<String> = (0..count)
+            .filter_map(|index| u32_at(table, list_offset + 2 + index * 6))
+            .map(tag_name)
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    };
+    (tag_list(u16_at(table, 4)), tag_list(u16_at(table, 6)))
+}
+
+pub const TRUETYPE_FLAVOR: u32 = 0x0001_0000;
+pub const CFF_FLAVOR: u32 = 0x4F54_544F; // 'OTTO'
+
+// The flavor of a font built from `tables`: CFF outlines make it an OpenType/CFF font.
+pub fn flavor_for_tables(tables: &[(u32, Vec<u8>)]) -> u32 {
+    if tables
+        .iter()
+        .any(|(table_tag, _)| *table_tag == tag(b"CFF ") || *table_tag == tag(b"CFF2"))
+    {
+        CFF_FLAVOR
+    } else {
+        TRUETYPE_FLAVOR
+    }
+}
+
+// Every table of `typeface` as it is seen by skia, i.e. after FreeType has decoded any WOFF/WOFF2
+// wrapping.
+pub fn all_tables(typeface: &Typeface) -> Vec<(u32, Vec<u8>)> {
+    typeface
+        .table_tags()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|table_tag| table_by_tag(typeface, table_tag).map(|data| (table_tag, data)))
+        .collect()
+}
+
+// Assembles an sfnt (.ttf/.otf) file with correct table checksums and head.checkSumAdjustment.
+pub fn write_sfnt(flavor: u32, tables: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut tables: Vec<(u32, Vec<u8>)> = tables.to_vec();
+    tables.sort_by_key(|(table_tag, _)| *table_tag);
+    let head_tag = tag(b"head");
+    for (table_tag, data) in tables.iter_mut() {
+        if *table_tag == head_tag && data.len() >= 12 {
+            data[8..12].copy_from_slice(&[0, 0, 0, 0]);
+        }
+    }
+
+    let table_count = tables.len() as u16;
+    let entry_selector = if table_count == 0 {
+        0
+    } else {
+        15 - table_count.leading_zeros() as u16
+    };
+    let search_range = (1u16 << entry_selector) * 16;
+    let mut font = vec![];
+    font.extend_from_slice(&flavor.to_be_bytes());
+    font.extend_from_slice(&table_count.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&(table_count * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = None;
+    for (table_tag, data) in tables.iter() {
+        if *table_tag == head_tag {
+            head_offset = Some(offset);
+        }
+        font.extend_from_slice(&table_tag.to_be_bytes());
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += padded_length(data.len());
+    }
+    for (_, data) in tables.iter() {
+        font.extend_from_slice(data);
+        font.resize(padded_length(font.len()), 0);
+    }
+
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+        font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+fn padded_length(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
@@ -0,0 +1,181 @@
+use crate::emoji::{apply_presentation, route_emoji, Presentation};
+use crate::fonts::FontEnvironment;
+use crate::line_spacing::LineSpacing;
+use crate::placeholders::{Placeholder, OBJECT_REPLACEMENT};
+use crate::text_effects::TextEffect;
+use skia_safe::textlayout::{Paragraph, ParagraphBuilder, ParagraphStyle, TextStyle};
+use std::ops::Range;
+
+// A run of text sharing one style.
+#[derive(Clone)]
+pub struct Span {
+    pub text: String,
+    pub style: TextStyle,
+    // How emoji characters in the span should be presented, see `emoji::route_emoji()`.
+    pub presentation: Presentation,
+    // Fill, stroke and background painted by `text_effects::paint_with_effects()` instead of the
+    // style's own foreground and background.
+    pub effect: Option<TextEffect>,
+    // Set for spans standing in for an inline box, whose text is a single object replacement
+    // character.
+    pub placeholder: Option<Placeholder>,
+}
+
+// Styled text that can be turned into a `Paragraph` any number of times, e.g. once per candidate
+// font size or width.
+#[derive(Clone)]
+pub struct StyledText {
+    pub paragraph_style: ParagraphStyle,
+    pub spans: Vec<Span>,
+}
+
+impl StyledText {
+    pub fn new(paragraph_style: ParagraphStyle) -> Self {
+        StyledText {
+            paragraph_style,
+            spans: vec![],
+        }
+    }
+
+    pub fn add_span(&mut self, text: impl Into<String>, style: TextStyle) -> &mut Self {
+        self.add_span_with_presentation(text, style, Presentation::Default)
+    }
+
+    // A forced presentation is written into the text as variation selectors here, rather than
+    // when the paragraph is built, so that indices into the paragraph match `text()`.
+    pub fn add_span_with_presentation(
+        &mut self,
+        text: impl Into<String>,
+        style: TextStyle,
+        presentation: Presentation,
+    ) -> &mut Self {
+        self.spans.push(Span {
+            text: apply_presentation(&text.into(), presentation),
+            style,
+            presentation,
+            effect: None,
+            placeholder: None,
+        });
+        self
+    }
+
+    pub fn add_span_with_effect(
+        &mut self,
+        text: impl Into<String>,
+        style: TextStyle,
+        effect: TextEffect,
+    ) -> &mut Self {
+        self.spans.push(Span {
+            text: text.into(),
+            style,
+            presentation: Presentation::Default,
+            effect: Some(effect),
+            placeholder: None,
+        });
+        self
+    }
+
+    // `style` sizes the line the placeholder sits on, as the style of the text around it would.
+    pub fn add_placeholder(&mut self, placeholder: Placeholder, style: TextStyle) -> &mut Self {
+        self.spans.push(Span {
+            text: OBJECT_REPLACEMENT.to_string(),
+            style,
+            presentation: Presentation::Default,
+            effect: None,
+            placeholder: Some(placeholder),
+        });
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    // The spans covering the UTF-8 `range` of `text()`, cut at its ends.
+    pub fn slice(&self, range: Range<usize>) -> StyledText {
+        let mut slice = StyledText::new(self.paragraph_style.clone());
+        let mut span_start = 0;
+        for span in self.spans.iter() {
+            let span_end = span_start + span.text.len();
+            let start = range.start.max(span_start);
+            let end = range.end.min(span_end);
+            if start < end {
+                let mut part = span.clone();
+                part.text = span.text[start - span_start..end - span_start].to_string();
+                slice.spans.push(part);
+            }
+            span_start = span_end;
+        }
+        slice
+    }
+
+    // The size of the first span. Other spans keep their size relative to it when the text is
+    // resized with `with_font_size()`.
+    pub fn base_font_size(&self) -> f32 {
+        self.spans
+            .first()
+            .map(|span| span.style.font_size())
+            .unwrap_or_else(|| self.paragraph_style.text_style().font_size())
+    }
+
+    // Text with a base size of zero has nothing to scale from and is returned unchanged.
+    pub fn with_font_size(&self, font_size: f32) -> StyledText {
+        let base_font_size = self.base_font_size();
+        if base_font_size <= 0.0 {
+            return self.clone();
+        }
+        let scale = font_size / base_font_size;
+        let mut resized = self.clone();
+        let mut default_style = resized.paragraph_style.text_style().clone();
+        default_style.set_font_size(default_style.font_size() * scale);
+        resized.paragraph_style.set_text_style(&default_style);
+        for span in resized.spans.iter_mut() {
+            let font_size = span.style.font_size() * scale;
+            span.style.set_font_size(font_size);
+            if let Some(placeholder) = span.placeholder.as_mut() {
+                placeholder.width *= scale;
+                placeholder.height *= scale;
+                placeholder.baseline_offset *= scale;
+            }
+        }
+        resized
+    }
+
+    pub fn set_line_spacing(&mut self, line_spacing: &LineSpacing) -> &mut Self {
+        line_spacing.apply(self);
+        self
+    }
+
+    // With an emoji font registered in `fonts`, emoji-presentation clusters are routed to it and
+    // everything else is kept away from it.
+    pub fn build(&self, fonts: &FontEnvironment) -> Paragraph {
+        let routed;
+        let text = match fonts.emoji_family() {
+            Some(emoji_family) => {
+                routed = route_emoji(self, emoji_family);
+                &routed
+            }
+            None => self,
+        };
+        let mut builder = ParagraphBuilder::new(&text.paragraph_style, fonts.font_collection());
+        for span in text.spans.iter() {
+            builder.push_style(&span.style);
+            match &span.placeholder {
+                Some(placeholder) => {
+                    builder.add_placeholder(&placeholder.style());
+                }
+                None => {
+                    builder.add_text(&span.text);
+                }
+            }
+            builder.pop();
+        }
+        builder.build()
+    }
+
+    pub fn layout(&self, fonts: &FontEnvironment, width: f32) -> Paragraph {
+        let mut paragraph = self.build(fonts);
+        paragraph.layout(width);
+        paragraph
+    }
+}
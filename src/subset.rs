@@ -0,0 +1,1572 @@
+use crate::data_from_file_path;
+use crate::sfnt::{
+    all_tables, cmap_mappings, cmap_variation_sequences, flavor_for_tables, i16_at, tag, tag_name,
+    u16_at, u32_at, write_sfnt,
+};
+use skia_safe::Typeface;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::path::Path;
+
+pub struct SubsetReport {
+    pub glyph_count: usize,
+    pub glyphs_kept: usize,
+    pub original_size: usize,
+    pub subset_size: usize,
+    // Tables and lookups that were dropped because they can't be rewritten for the new glyph ids.
+    pub warnings: Vec<String>,
+}
+
+// Tables that don't refer to glyph ids and are copied as they are.
+const GLYPH_INDEPENDENT_TABLES: [&[u8; 4]; 10] = [
+    b"OS/2", b"name", b"cvt ", b"fpgm", b"prep", b"gasp", b"CPAL", b"meta", b"VDMX", b"FFTM",
+];
+
+const USE_MARK_FILTERING_SET: u16 = 0x0010;
+
+// Builds a font that only contains the glyphs needed to render `text`.
+//
+// The glyph set is closed over:
+// - variation sequences (cmap format 14) of characters in `text`,
+// - every GSUB substitution whose input glyphs are all in the set, regardless of the lookup's
+//   context, so ligatures and contextual forms are kept even if they are never triggered,
+// - COLRv0 layers of colour glyphs,
+// - components of composite glyphs and the targets of sbix 'dupe' glyphs.
+//
+// The kept glyphs are then renumbered in their original order, so .notdef stays glyph 0, and
+// every table that refers to glyph ids is rebuilt for the new numbering: glyf/loca, hmtx/vmtx
+// and the metric counts in hhea/vhea/maxp, cmap, COLRv0, CBLC/CBDT, EBLC/EBDT, sbix, the GSUB
+// lookups and the class definitions and mark glyph sets of GDEF. post is reduced to version 3,
+// without glyph names. Reverse chaining substitutions are emptied, and GPOS, kern, SVG and the
+// variation tables are dropped, with a warning for each.
+//
+// Only TrueType outlines (glyf/loca) and bitmap glyphs are supported.
+pub fn subset(file_path: &Path, text: &str) -> Result<(Vec<u8>, SubsetReport), String> {
+    let data = data_from_file_path(file_path);
+    let original_size = data.size();
+    let typeface = Typeface::from_data(data, None)
+        .ok_or_else(|| format!("skia failed to load {}", file_path.display()))?;
+    let tables = all_tables(&typeface);
+    let find = |name: &[u8; 4]| -> Option<&[u8]> {
+        tables
+            .iter()
+            .find(|(table_tag, _)| *table_tag == tag(name))
+            .map(|(_, data)| data.as_slice())
+    };
+
+    let (head, maxp, hhea, hmtx) =
+        match (find(b"head"), find(b"maxp"), find(b"hhea"), find(b"hmtx")) {
+            (Some(head), Some(maxp), Some(hhea), Some(hmtx)) => (head, maxp, hhea, hmtx),
+            _ => {
+                return Err(format!(
+                    "{}: the head, maxp, hhea or hmtx table is missing",
+                    file_path.display()
+                ))
+            }
+        };
+    if head.len() < 54 || maxp.len() < 6 || hhea.len() < 36 {
+        return Err(format!(
+            "{}: the head, maxp or hhea table is truncated",
+            file_path.display()
+        ));
+    }
+    let has_bitmaps = [b"CBLC", b"EBLC", b"sbix"]
+        .iter()
+        .any(|name| find(name).is_some());
+    let outlines = match (find(b"glyf"), find(b"loca")) {
+        (Some(glyf), Some(loca)) => Some((glyf, loca)),
+        _ if has_bitmaps && find(b"CFF ").is_none() && find(b"CFF2").is_none() => None,
+        _ => {
+            return Err(format!(
+                "{}: only fonts with TrueType outlines (glyf/loca) or bitmap glyphs can be subset",
+                file_path.display()
+            ))
+        }
+    };
+    let glyph_count = u16_at(maxp, 4).unwrap_or(0) as usize;
+    let long_offsets = i16_at(head, 50) == Some(1);
+    let glyph_offsets: Vec<usize> = match outlines {
+        Some((_, loca)) => (0..=glyph_count)
+            .map(|glyph| {
+                if long_offsets {
+                    u32_at(loca, glyph * 4).unwrap_or(0) as usize
+                } else {
+                    u16_at(loca, glyph * 2).unwrap_or(0) as usize * 2
+                }
+            })
+            .collect(),
+        None => vec![],
+    };
+    let cmap = find(b"cmap").unwrap_or_default();
+    let mut code_points: Vec<u32> = text.chars().map(|character| character as u32).collect();
+    code_points.sort_unstable();
+    code_points.dedup();
+    let kept = |code_point: &u32| code_points.binary_search(code_point).is_ok();
+    let kept_mappings: Vec<(u32, u16)> = cmap_mappings(cmap)
+        .into_iter()
+        .filter(|(code_point, _)| kept(code_point))
+        .collect();
+    let kept_sequences: Vec<(u32, u32, Option<u16>)> = cmap_variation_sequences(cmap)
+        .into_iter()
+        .filter(|(selector, base, _)| kept(selector) && kept(base))
+        .collect();
+
+    let mut glyphs: BTreeSet<u16> = kept_mappings.iter().map(|(_, glyph)| *glyph).collect();
+    glyphs.extend(kept_sequences.iter().filter_map(|(_, _, glyph)| *glyph));
+    glyphs.insert(0);
+    let lookups = find(b"GSUB").map(read_lookups).unwrap_or_default();
+    let colr = find(b"COLR");
+    let sbix = find(b"sbix");
+    if let Some(colr) = colr {
+        if u16_at(colr, 0).unwrap_or(0) > 0 {
+            return Err(format!(
+                "{}: COLRv1 paint graphs aren't supported by the subsetter",
+                file_path.display()
+            ));
+        }
+    }
+    loop {
+        let size = glyphs.len();
+        for lookup in lookups.iter() {
+            for subtable in lookup.subtables.iter() {
+                substitution_closure(subtable, lookup.lookup_type, &mut glyphs);
+            }
+        }
+        if let Some(colr) = colr {
+            colr_v0_closure(colr, &mut glyphs);
+        }
+        if let Some((glyf, _)) = outlines {
+            let components: Vec<u16> = glyphs
+                .iter()
+                .flat_map(|glyph| composite_components(glyph_data(glyf, &glyph_offsets, *glyph)))
+                .collect();
+            glyphs.extend(components);
+        }
+        if let Some(sbix) = sbix {
+            sbix_dupe_closure(sbix, glyph_count, &mut glyphs);
+        }
+        glyphs.retain(|glyph| (*glyph as usize) < glyph_count);
+        if glyphs.len() == size {
+            break;
+        }
+    }
+    let glyph_map = GlyphMap::new(&glyphs, glyph_count);
+
+    let new_outlines = outlines.map(|(glyf, _)| subset_glyf(glyf, &glyph_offsets, &glyph_map));
+    let bitmaps = |location: &[u8; 4], data: &[u8; 4]| match (find(location), find(data)) {
+        (Some(location), Some(data)) => subset_bitmaps(location, data, &glyph_map)
+            .map(Some)
+            .map_err(|reason| format!("{}: {}", file_path.display(), reason)),
+        _ => Ok(None),
+    };
+    let colour_bitmaps = bitmaps(b"CBLC", b"CBDT")?;
+    let mono_bitmaps = bitmaps(b"EBLC", b"EBDT")?;
+    let mappings: Vec<(u32, u16)> = kept_mappings
+        .iter()
+        .filter_map(|(code_point, glyph)| Some((*code_point, glyph_map.get(*glyph)?)))
+        .collect();
+    let sequences: Vec<(u32, u32, Option<u16>)> = kept_sequences
+        .iter()
+        .filter_map(|(selector, base, glyph)| match glyph {
+            Some(glyph) => Some((*selector, *base, Some(glyph_map.get(*glyph)?))),
+            None => Some((*selector, *base, None)),
+        })
+        .collect();
+    let with_metric_count = |table: &[u8]| {
+        let mut table = table.to_vec();
+        if table.len() >= 36 {
+            table[34..36].copy_from_slice(&(glyph_map.len() as u16).to_be_bytes());
+        }
+        table
+    };
+
+    let mut warnings = vec![];
+    let mut subset_tables = vec![];
+    for (table_tag, data) in tables.iter() {
+        let subset = match &table_tag.to_be_bytes() {
+            b"head" => {
+                let mut head = data.clone();
+                if new_outlines.is_some() {
+                    head[50..52].copy_from_slice(&1i16.to_be_bytes());
+                }
+                Some(head)
+            }
+            b"maxp" => {
+                let mut maxp = data.clone();
+                maxp[4..6].copy_from_slice(&(glyph_map.len() as u16).to_be_bytes());
+                Some(maxp)
+            }
+            b"hhea" | b"vhea" => Some(with_metric_count(data)),
+            b"hmtx" => Some(subset_metrics(
+                hmtx,
+                u16_at(hhea, 34).unwrap_or(0) as usize,
+                &glyph_map,
+            )),
+            b"vmtx" => find(b"vhea").map(|vhea| {
+                subset_metrics(data, u16_at(vhea, 34).unwrap_or(0) as usize, &glyph_map)
+            }),
+            b"glyf" => new_outlines.as_ref().map(|(glyf, _)| glyf.clone()),
+            b"loca" => new_outlines.as_ref().map(|(_, loca)| loca.clone()),
+            b"CBLC" => colour_bitmaps
+                .as_ref()
+                .map(|(location, _)| location.clone()),
+            b"CBDT" => colour_bitmaps.as_ref().map(|(_, data)| data.clone()),
+            b"EBLC" => mono_bitmaps.as_ref().map(|(location, _)| location.clone()),
+            b"EBDT" => mono_bitmaps.as_ref().map(|(_, data)| data.clone()),
+            b"sbix" => Some(subset_sbix(data, glyph_count, &glyph_map)),
+            b"cmap" => Some(subset_cmap(&mappings, &sequences)),
+            b"COLR" => Some(subset_colr(data, &glyph_map)),
+            b"GSUB" => match subset_gsub(data, &lookups, &glyph_map, &mut warnings) {
+                Ok(gsub) => Some(gsub),
+                Err(reason) => {
+                    warnings.push(reason);
+                    None
+                }
+            },
+            b"GDEF" => subset_gdef(data, &glyph_map),
+            b"post" if data.len() >= 32 => {
+                // Version 3 has no glyph names, just the header.
+                let mut post = data[..32].to_vec();
+                post[0..4].copy_from_slice(&0x0003_0000u32.to_be_bytes());
+                Some(post)
+            }
+            // A signature can't be valid for modified tables.
+            b"DSIG" => continue,
+            name if GLYPH_INDEPENDENT_TABLES.contains(&name) => Some(data.clone()),
+            _ => None,
+        };
+        match subset {
+            Some(subset) => subset_tables.push((*table_tag, subset)),
+            None => warnings.push(format!(
+                "{} was dropped, it can't be rewritten for the new glyph ids",
+                tag_name(*table_tag).trim_end()
+            )),
+        }
+    }
+
+    let font = write_sfnt(flavor_for_tables(&subset_tables), &subset_tables);
+    let report = SubsetReport {
+        glyph_count,
+        glyphs_kept: glyph_map.len(),
+        original_size,
+        subset_size: font.len(),
+        warnings,
+    };
+    Ok((font, report))
+}
+
+pub fn write_subset(
+    file_path: &Path,
+    text: &str,
+    output_path: &Path,
+) -> Result<SubsetReport, String> {
+    let (font, report) = subset(file_path, text)?;
+    std::fs::write(output_path, &font)
+        .map_err(|error| format!("failed to write {}: {}", output_path.display(), error))?;
+    Ok(report)
+}
+
+// The kept glyphs, renumbered in their original order. That keeps glyph lists which have to be
+// sorted by glyph id (coverage tables, COLR base glyph records) sorted after renumbering.
+struct GlyphMap {
+    new_ids: Vec<Option<u16>>,
+    // The original id of each new glyph.
+    old_ids: Vec<u16>,
+}
+
+impl GlyphMap {
+    fn new(glyphs: &BTreeSet<u16>, glyph_count: usize) -> Self {
+        let old_ids: Vec<u16> = glyphs
+            .iter()
+            .copied()
+            .filter(|glyph| (*glyph as usize) < glyph_count)
+            .collect();
+        let mut new_ids = vec![None; glyph_count];
+        for (new_id, old_id) in old_ids.iter().enumerate() {
+            new_ids[*old_id as usize] = Some(new_id as u16);
+        }
+        GlyphMap { new_ids, old_ids }
+    }
+
+    fn get(&self, glyph: u16) -> Option<u16> {
+        self.new_ids.get(glyph as usize).copied().flatten()
+    }
+
+    fn len(&self) -> usize {
+        self.old_ids.len()
+    }
+}
+
+fn push_u16(table: &mut Vec<u8>, value: u16) {
+    table.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_u32(table: &mut Vec<u8>, value: u32) {
+    table.extend_from_slice(&value.to_be_bytes());
+}
+
+// Appends each child table to `table` and writes its 16-bit offset, relative to the start of
+// `table`, at the given position. None if an offset doesn't fit in 16 bits.
+fn append_children(table: &mut Vec<u8>, children: Vec<(usize, Vec<u8>)>) -> Option<()> {
+    for (field, child) in children {
+        let offset = u16::try_from(table.len()).ok()?;
+        table[field..field + 2].copy_from_slice(&offset.to_be_bytes());
+        table.extend(child);
+    }
+    Some(())
+}
+
+// glyf and long-offset loca with the kept glyphs in their new order.
+fn subset_glyf(glyf: &[u8], glyph_offsets: &[usize], glyph_map: &GlyphMap) -> (Vec<u8>, Vec<u8>) {
+    let mut new_glyf = vec![];
+    let mut new_loca = vec![];
+    for glyph in glyph_map.old_ids.iter() {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        let mut data = glyph_data(glyf, glyph_offsets, *glyph).to_vec();
+        for offset in component_offsets(&data) {
+            let component = u16_at(&data, offset).and_then(|component| glyph_map.get(component));
+            data[offset..offset + 2].copy_from_slice(&component.unwrap_or(0).to_be_bytes());
+        }
+        new_glyf.extend(data);
+        // Glyph data has to stay 4-byte aligned with long loca offsets.
+        new_glyf.resize((new_glyf.len() + 3) & !3, 0);
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+    (new_glyf, new_loca)
+}
+
+// hmtx or vmtx with a full metric record for every kept glyph, so the hhea/vhea metric count
+// becomes the glyph count.
+fn subset_metrics(metrics: &[u8], long_metric_count: usize, glyph_map: &GlyphMap) -> Vec<u8> {
+    // Glyphs past the long metrics share the last advance and only have a side bearing.
+    let last_advance = long_metric_count
+        .checked_sub(1)
+        .and_then(|last| u16_at(metrics, last * 4))
+        .unwrap_or(0);
+    let mut subset = vec![];
+    for glyph in glyph_map.old_ids.iter() {
+        let glyph = *glyph as usize;
+        let (advance, bearing) = if glyph < long_metric_count {
+            (u16_at(metrics, glyph * 4), u16_at(metrics, glyph * 4 + 2))
+        } else {
+            let bearing = long_metric_count * 4 + (glyph - long_metric_count) * 2;
+            (Some(last_advance), u16_at(metrics, bearing))
+        };
+        push_u16(&mut subset, advance.unwrap_or(0));
+        push_u16(&mut subset, bearing.unwrap_or(0));
+    }
+    subset
+}
+
+// A cmap with a format 12 subtable, referenced as both Unicode full repertoire and Windows
+// UCS-4, and a format 14 subtable for `sequences` if there are any.
+fn subset_cmap(mappings: &[(u32, u16)], sequences: &[(u32, u32, Option<u16>)]) -> Vec<u8> {
+    let format_12 = cmap_format_12(mappings);
+    let mut records = vec![(0u16, 4u16, 0usize)];
+    let mut subtables = vec![format_12];
+    if !sequences.is_empty() {
+        records.push((0, 5, 1));
+        subtables.push(cmap_format_14(sequences));
+    }
+    records.push((3, 10, 0));
+
+    let mut cmap = vec![];
+    cmap.extend_from_slice(&0u16.to_be_bytes());
+    cmap.extend_from_slice(&(records.len() as u16).to_be_bytes());
+    let mut offsets = vec![];
+    let mut offset = 4 + records.len() * 8;
+    for subtable in subtables.iter() {
+        offsets.push(offset as u32);
+        offset += subtable.len();
+    }
+    for (platform, encoding, subtable) in records {
+        cmap.extend_from_slice(&platform.to_be_bytes());
+        cmap.extend_from_slice(&encoding.to_be_bytes());
+        cmap.extend_from_slice(&offsets[subtable].to_be_bytes());
+    }
+    for subtable in subtables {
+        cmap.extend(subtable);
+    }
+    cmap
+}
+
+fn cmap_format_12(mappings: &[(u32, u16)]) -> Vec<u8> {
+    let mut groups: Vec<(u32, u32, u32)> = vec![];
+    for (code_point, glyph) in mappings.iter() {
+        match groups.last_mut() {
+            Some((start, end, start_glyph))
+                if *end + 1 == *code_point
+                    && *start_glyph + (*code_point - *start) == *glyph as u32 =>
+            {
+                *end = *code_point
+            }
+            _ => groups.push((*code_point, *code_point, *glyph as u32)),
+        }
+    }
+    let mut subtable = vec![];
+    subtable.extend_from_slice(&12u16.to_be_bytes());
+    subtable.extend_from_slice(&0u16.to_be_bytes());
+    subtable.extend_from_slice(&(16 + groups.len() as u32 * 12).to_be_bytes());
+    subtable.extend_from_slice(&0u32.to_be_bytes());
+    subtable.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for (start, end, start_glyph) in groups {
+        subtable.extend_from_slice(&start.to_be_bytes());
+        subtable.extend_from_slice(&end.to_be_bytes());
+        subtable.extend_from_slice(&start_glyph.to_be_bytes());
+    }
+    subtable
+}
+
+// `sequences` must be sorted by selector and base, as `cmap_variation_sequences()` returns them.
+fn cmap_format_14(sequences: &[(u32, u32, Option<u16>)]) -> Vec<u8> {
+    let mut selectors: Vec<u32> = sequences.iter().map(|(selector, _, _)| *selector).collect();
+    selectors.dedup();
+    let header_length = 10 + selectors.len() * 11;
+    let mut records = vec![];
+    let mut lists = vec![];
+    for selector in selectors {
+        let mut default_ranges: Vec<(u32, u8)> = vec![];
+        let mut glyph_mappings = vec![];
+        for (_, base, glyph) in sequences.iter().filter(|(other, _, _)| *other == selector) {
+            match glyph {
+                Some(glyph) => glyph_mappings.push((*base, *glyph)),
+                None => match default_ranges.last_mut() {
+                    Some((start, additional))
+                        if *start + *additional as u32 + 1 == *base && *additional < 255 =>
+                    {
+                        *additional += 1
+                    }
+                    _ => default_ranges.push((*base, 0)),
+                },
+            }
+        }
+        records.extend_from_slice(&selector.to_be_bytes()[1..]);
+        if default_ranges.is_empty() {
+            records.extend_from_slice(&0u32.to_be_bytes());
+        } else {
+            records.extend_from_slice(&((header_length + lists.len()) as u32).to_be_bytes());
+            lists.extend_from_slice(&(default_ranges.len() as u32).to_be_bytes());
+            for (start, additional) in default_ranges {
+                lists.extend_from_slice(&start.to_be_bytes()[1..]);
+                lists.push(additional);
+            }
+        }
+        if glyph_mappings.is_empty() {
+            records.extend_from_slice(&0u32.to_be_bytes());
+        } else {
+            records.extend_from_slice(&((header_length + lists.len()) as u32).to_be_bytes());
+            lists.extend_from_slice(&(glyph_mappings.len() as u32).to_be_bytes());
+            for (base, glyph) in glyph_mappings {
+                lists.extend_from_slice(&base.to_be_bytes()[1..]);
+                lists.extend_from_slice(&glyph.to_be_bytes());
+            }
+        }
+    }
+    let mut subtable = vec![];
+    subtable.extend_from_slice(&14u16.to_be_bytes());
+    subtable.extend_from_slice(&((header_length + lists.len()) as u32).to_be_bytes());
+    subtable.extend_from_slice(&((records.len() / 11) as u32).to_be_bytes());
+    subtable.extend(records);
+    subtable.extend(lists);
+    subtable
+}
+
+struct Lookup<'a> {
+    // The type of the subtables, looked through extension substitutions.
+    lookup_type: u16,
+    flag: u16,
+    mark_filtering_set: Option<u16>,
+    subtables: Vec<&'a [u8]>,
+}
+
+// The lookups of a GSUB table, by lookup index. A lookup that can't be read has no subtables.
+fn read_lookups(gsub: &[u8]) -> Vec<Lookup<'_>> {
+    let lookup_list = u16_at(gsub, 8).unwrap_or(0) as usize;
+    if lookup_list == 0 {
+        return vec![];
+    }
+    let lookup_count = u16_at(gsub, lookup_list).unwrap_or(0) as usize;
+    let mut lookups = vec![];
+    for lookup_index in 0..lookup_count {
+        let lookup =
+            lookup_list + u16_at(gsub, lookup_list + 2 + lookup_index * 2).unwrap_or(0) as usize;
+        let mut lookup_type = u16_at(gsub, lookup).unwrap_or(0);
+        let flag = u16_at(gsub, lookup + 2).unwrap_or(0);
+        let subtable_count = u16_at(gsub, lookup + 4).unwrap_or(0) as usize;
+        let mut subtables = vec![];
+        for subtable_index in 0..subtable_count {
+            let mut subtable = match u16_at(gsub, lookup + 6 + subtable_index * 2) {
+                Some(offset) => lookup + offset as usize,
+                None => break,
+            };
+            // Extension substitution: the real subtable is behind a 32-bit offset.
+            if u16_at(gsub, lookup) == Some(7) {
+                lookup_type = u16_at(gsub, subtable + 2).unwrap_or(0);
+                subtable += u32_at(gsub, subtable + 4).unwrap_or(0) as usize;
+            }
+            if let Some(data) = gsub.get(subtable..) {
+                subtables.push(data);
+            }
+        }
+        let mark_filtering_set = if flag & USE_MARK_FILTERING_SET != 0 {
+            u16_at(gsub, lookup + 6 + subtable_count * 2)
+        } else {
+            None
+        };
+        lookups.push(Lookup {
+            lookup_type,
+            flag,
+            mark_filtering_set,
+            subtables,
+        });
+    }
+    lookups
+}
+
+// Adds the output glyphs of a substitution subtable whose inputs are in `glyphs`. Contextual
+// lookups (types 5 and 6) only reference other lookups, which are visited anyway.
+fn substitution_closure(subtable: &[u8], lookup_type: u16, glyphs: &mut BTreeSet<u16>) {
+    let mut added = vec![];
+    match lookup_type {
+        1 => {
+            for (input, output) in single_substitutions(subtable).unwrap_or_default() {
+                if glyphs.contains(&input) {
+                    added.push(output);
+                }
+            }
+        }
+        2 | 3 => {
+            for (input, outputs) in glyph_sequences(subtable).unwrap_or_default() {
+                if glyphs.contains(&input) {
+                    added.extend(outputs);
+                }
+            }
+        }
+        4 => {
+            for (first, ligatures) in ligature_sets(subtable).unwrap_or_default() {
+                if !glyphs.contains(&first) {
+                    continue;
+                }
+                for (components, ligature) in ligatures {
+                    if components
+                        .iter()
+                        .all(|component| glyphs.contains(component))
+                    {
+                        added.push(ligature);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    glyphs.extend(added);
+}
+
+// (input, output) pairs of a single substitution subtable, None for an unknown format.
+fn single_substitutions(subtable: &[u8]) -> Option<Vec<(u16, u16)>> {
+    let coverage = coverage(subtable, u16_at(subtable, 2)? as usize);
+    match u16_at(subtable, 0)? {
+        // By delta.
+        1 => {
+            let delta = i16_at(subtable, 4)?;
+            Some(
+                coverage
+                    .into_iter()
+                    .map(|glyph| (glyph, (glyph as i32 + delta as i32) as u16))
+                    .collect(),
+            )
+        }
+        // By list.
+        2 => Some(
+            coverage
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, glyph)| Some((glyph, u16_at(subtable, 6 + index * 2)?)))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+// The glyph list of each covered glyph in a multiple or alternate substitution subtable.
+fn glyph_sequences(subtable: &[u8]) -> Option<Vec<(u16, Vec<u16>)>> {
+    if u16_at(subtable, 0)? != 1 {
+        return None;
+    }
+    let coverage = coverage(subtable, u16_at(subtable, 2)? as usize);
+    Some(
+        coverage
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, glyph)| {
+                let sequence = u16_at(subtable, 6 + index * 2)? as usize;
+                Some((glyph, glyph_array(subtable, sequence)))
+            })
+            .collect(),
+    )
+}
+
+// The components after the first one and the ligature glyph.
+type Ligature = (Vec<u16>, u16);
+
+// The ligatures of a ligature substitution subtable by first glyph.
+fn ligature_sets(subtable: &[u8]) -> Option<Vec<(u16, Vec<Ligature>)>> {
+    if u16_at(subtable, 0)? != 1 {
+        return None;
+    }
+    let coverage = coverage(subtable, u16_at(subtable, 2)? as usize);
+    let mut sets = vec![];
+    for (index, first) in coverage.into_iter().enumerate() {
+        let ligature_set = u16_at(subtable, 6 + index * 2)? as usize;
+        let ligature_count = u16_at(subtable, ligature_set).unwrap_or(0) as usize;
+        let mut ligatures = vec![];
+        for ligature_index in 0..ligature_count {
+            let ligature = match u16_at(subtable, ligature_set + 2 + ligature_index * 2) {
+                Some(offset) => ligature_set + offset as usize,
+                None => break,
+            };
+            let component_count = u16_at(subtable, ligature + 2).unwrap_or(0) as usize;
+            let components: Option<Vec<u16>> = (1..component_count)
+                .map(|component| u16_at(subtable, ligature + 2 + component * 2))
+                .collect();
+            if let (Some(components), Some(glyph)) = (components, u16_at(subtable, ligature)) {
+                ligatures.push((components, glyph));
+            }
+        }
+        sets.push((first, ligatures));
+    }
+    Some(sets)
+}
+
+// GSUB with its lookups rewritten for the new glyph ids. The script and feature lists don't
+// refer to glyphs and are copied as they are, so lookup indices stay the same. Subtables that
+// can't be rewritten are dropped from their lookup with a warning.
+fn subset_gsub(
+    gsub: &[u8],
+    lookups: &[Lookup],
+    glyph_map: &GlyphMap,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<u8>, String> {
+    let truncated = || "GSUB was dropped, its header is truncated".to_string();
+    let too_large = || "GSUB was dropped, the subset doesn't fit its 16-bit offsets".to_string();
+    let script_list = u16_at(gsub, 4).ok_or_else(truncated)? as usize;
+    let feature_list = u16_at(gsub, 6).ok_or_else(truncated)? as usize;
+    let lookup_list = u16_at(gsub, 8).ok_or_else(truncated)? as usize;
+    let lists_start = script_list.min(feature_list);
+    if lists_start < 10 || lookup_list <= script_list.max(feature_list) {
+        return Err("GSUB was dropped, its lookups don't come after the feature list".to_string());
+    }
+
+    let mut table = vec![];
+    push_u16(&mut table, 1);
+    push_u16(&mut table, 0);
+    push_u16(&mut table, (script_list - lists_start + 10) as u16);
+    push_u16(&mut table, (feature_list - lists_start + 10) as u16);
+    push_u16(&mut table, 0);
+    table.extend_from_slice(gsub.get(lists_start..lookup_list).ok_or_else(truncated)?);
+
+    let mut lookup_list_table = vec![];
+    push_u16(&mut lookup_list_table, lookups.len() as u16);
+    lookup_list_table.resize(2 + lookups.len() * 2, 0);
+    let mut lookup_tables = vec![];
+    for (lookup_index, lookup) in lookups.iter().enumerate() {
+        let mut subtables = vec![];
+        for subtable in lookup.subtables.iter() {
+            let subset = match lookup.lookup_type {
+                1 => single_substitution(subtable, glyph_map),
+                2 | 3 => sequence_substitution(subtable, lookup.lookup_type, glyph_map),
+                4 => ligature_substitution(subtable, glyph_map),
+                5 | 6 => context_substitution(subtable, lookup.lookup_type == 6, glyph_map),
+                _ => None,
+            };
+            subtables.extend(subset);
+        }
+        if subtables.len() < lookup.subtables.len() {
+            warnings.push(format!(
+                "GSUB lookup {} (type {}) lost subtables that can't be subset",
+                lookup_index, lookup.lookup_type
+            ));
+        }
+        let mut lookup_table = vec![];
+        push_u16(&mut lookup_table, lookup.lookup_type);
+        push_u16(&mut lookup_table, lookup.flag);
+        push_u16(&mut lookup_table, subtables.len() as u16);
+        lookup_table.resize(6 + subtables.len() * 2, 0);
+        if let Some(mark_filtering_set) = lookup.mark_filtering_set {
+            push_u16(&mut lookup_table, mark_filtering_set);
+        }
+        let fields = (0..subtables.len()).map(|index| 6 + index * 2);
+        append_children(&mut lookup_table, fields.zip(subtables).collect())
+            .ok_or_else(too_large)?;
+        lookup_tables.push((2 + lookup_index * 2, lookup_table));
+    }
+    append_children(&mut lookup_list_table, lookup_tables).ok_or_else(too_large)?;
+    append_children(&mut table, vec![(8, lookup_list_table)]).ok_or_else(too_large)?;
+    Ok(table)
+}
+
+// The subset subtables below are None if their format is unknown or they don't fit their
+// 16-bit offsets.
+
+fn single_substitution(subtable: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let substitutions: Vec<(u16, u16)> = single_substitutions(subtable)?
+        .into_iter()
+        .filter_map(|(input, output)| Some((glyph_map.get(input)?, glyph_map.get(output)?)))
+        .collect();
+    let mut table = vec![];
+    push_u16(&mut table, 2);
+    push_u16(&mut table, 0);
+    push_u16(&mut table, substitutions.len() as u16);
+    for (_, output) in substitutions.iter() {
+        push_u16(&mut table, *output);
+    }
+    let inputs: Vec<u16> = substitutions.iter().map(|(input, _)| *input).collect();
+    append_children(&mut table, vec![(2, coverage_table(&inputs))])?;
+    Some(table)
+}
+
+fn sequence_substitution(
+    subtable: &[u8],
+    lookup_type: u16,
+    glyph_map: &GlyphMap,
+) -> Option<Vec<u8>> {
+    let mut sequences = vec![];
+    for (input, outputs) in glyph_sequences(subtable)? {
+        let input = match glyph_map.get(input) {
+            Some(input) => input,
+            None => continue,
+        };
+        let kept: Vec<u16> = outputs
+            .iter()
+            .filter_map(|glyph| glyph_map.get(*glyph))
+            .collect();
+        // A multiple substitution needs all of its glyphs, an alternate set any of them.
+        let complete = if lookup_type == 2 {
+            kept.len() == outputs.len()
+        } else {
+            !kept.is_empty()
+        };
+        if complete {
+            sequences.push((input, kept));
+        }
+    }
+    let mut table = vec![];
+    push_u16(&mut table, 1);
+    push_u16(&mut table, 0);
+    push_u16(&mut table, sequences.len() as u16);
+    table.resize(6 + sequences.len() * 2, 0);
+    let inputs: Vec<u16> = sequences.iter().map(|(input, _)| *input).collect();
+    let mut children = vec![(2, coverage_table(&inputs))];
+    for (index, (_, glyphs)) in sequences.into_iter().enumerate() {
+        let mut sequence = vec![];
+        push_u16(&mut sequence, glyphs.len() as u16);
+        for glyph in glyphs {
+            push_u16(&mut sequence, glyph);
+        }
+        children.push((6 + index * 2, sequence));
+    }
+    append_children(&mut table, children)?;
+    Some(table)
+}
+
+fn ligature_substitution(subtable: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let mut sets = vec![];
+    for (first, ligatures) in ligature_sets(subtable)? {
+        let first = match glyph_map.get(first) {
+            Some(first) => first,
+            None => continue,
+        };
+        let kept: Vec<Ligature> = ligatures
+            .into_iter()
+            .filter_map(|(components, ligature)| {
+                let components: Option<Vec<u16>> = components
+                    .iter()
+                    .map(|component| glyph_map.get(*component))
+                    .collect();
+                Some((components?, glyph_map.get(ligature)?))
+            })
+            .collect();
+        if !kept.is_empty() {
+            sets.push((first, kept));
+        }
+    }
+    let mut table = vec![];
+    push_u16(&mut table, 1);
+    push_u16(&mut table, 0);
+    push_u16(&mut table, sets.len() as u16);
+    table.resize(6 + sets.len() * 2, 0);
+    let firsts: Vec<u16> = sets.iter().map(|(first, _)| *first).collect();
+    let mut children = vec![(2, coverage_table(&firsts))];
+    for (index, (_, ligatures)) in sets.into_iter().enumerate() {
+        let mut set = vec![];
+        push_u16(&mut set, ligatures.len() as u16);
+        set.resize(2 + ligatures.len() * 2, 0);
+        let mut ligature_tables = vec![];
+        for (ligature_index, (components, glyph)) in ligatures.into_iter().enumerate() {
+            let mut ligature = vec![];
+            push_u16(&mut ligature, glyph);
+            push_u16(&mut ligature, components.len() as u16 + 1);
+            for component in components {
+                push_u16(&mut ligature, component);
+            }
+            ligature_tables.push((2 + ligature_index * 2, ligature));
+        }
+        append_children(&mut set, ligature_tables)?;
+        children.push((6 + index * 2, set));
+    }
+    append_children(&mut table, children)?;
+    Some(table)
+}
+
+// A contextual (type 5) or chained contextual (type 6) substitution subtable. The lookups they
+// apply keep their indices, so only the coverage, class definitions and glyph rules change.
+fn context_substitution(subtable: &[u8], chained: bool, glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let mut table = vec![];
+    let mut children = vec![];
+    match u16_at(subtable, 0)? {
+        // Glyph rules, with a rule set per covered glyph.
+        1 => {
+            let covered = coverage(subtable, u16_at(subtable, 2)? as usize);
+            let mut inputs = vec![];
+            let mut rule_sets = vec![];
+            for (index, glyph) in covered.into_iter().enumerate() {
+                if let Some(glyph) = glyph_map.get(glyph) {
+                    inputs.push(glyph);
+                    rule_sets.push(u16_at(subtable, 6 + index * 2)? as usize);
+                }
+            }
+            push_u16(&mut table, 1);
+            push_u16(&mut table, 0);
+            push_u16(&mut table, rule_sets.len() as u16);
+            table.resize(6 + rule_sets.len() * 2, 0);
+            children.push((2, coverage_table(&inputs)));
+            for (index, rule_set_offset) in rule_sets.into_iter().enumerate() {
+                if rule_set_offset != 0 {
+                    let set = rule_set(subtable, rule_set_offset, |rule| {
+                        sequence_rule(subtable, rule, chained, Some(glyph_map))
+                    })?;
+                    children.push((6 + index * 2, set));
+                }
+            }
+        }
+        // Class rules, with a rule set per input class.
+        2 => {
+            let class_def_count = if chained { 3 } else { 1 };
+            let rule_set_count_field = 4 + class_def_count * 2;
+            let rule_set_count = u16_at(subtable, rule_set_count_field)? as usize;
+            push_u16(&mut table, 2);
+            table.resize(rule_set_count_field, 0);
+            push_u16(&mut table, rule_set_count as u16);
+            table.resize(rule_set_count_field + 2 + rule_set_count * 2, 0);
+            children.push((
+                2,
+                mapped_coverage(subtable, u16_at(subtable, 2)? as usize, glyph_map),
+            ));
+            for index in 0..class_def_count {
+                let offset = u16_at(subtable, 4 + index * 2)? as usize;
+                if offset != 0 {
+                    let classes = mapped_classes(&class_def(subtable, offset), glyph_map);
+                    children.push((4 + index * 2, class_def_table(&classes)));
+                }
+            }
+            for index in 0..rule_set_count {
+                let field = rule_set_count_field + 2 + index * 2;
+                let rule_set_offset = u16_at(subtable, field)? as usize;
+                if rule_set_offset != 0 {
+                    let set = rule_set(subtable, rule_set_offset, |rule| {
+                        sequence_rule(subtable, rule, chained, None)
+                    })?;
+                    children.push((field, set));
+                }
+            }
+        }
+        // A single rule with a coverage per glyph position.
+        3 => {
+            push_u16(&mut table, 3);
+            let mut position = 2;
+            let lookup_count = if chained {
+                for _ in 0..3 {
+                    let count = u16_at(subtable, position)? as usize;
+                    push_u16(&mut table, count as u16);
+                    position += 2;
+                    for _ in 0..count {
+                        let offset = u16_at(subtable, position)? as usize;
+                        children.push((table.len(), mapped_coverage(subtable, offset, glyph_map)));
+                        push_u16(&mut table, 0);
+                        position += 2;
+                    }
+                }
+                let lookup_count = u16_at(subtable, position)? as usize;
+                push_u16(&mut table, lookup_count as u16);
+                position += 2;
+                lookup_count
+            } else {
+                let glyph_count = u16_at(subtable, 2)? as usize;
+                let lookup_count = u16_at(subtable, 4)? as usize;
+                push_u16(&mut table, glyph_count as u16);
+                push_u16(&mut table, lookup_count as u16);
+                position += 4;
+                for _ in 0..glyph_count {
+                    let offset = u16_at(subtable, position)? as usize;
+                    children.push((table.len(), mapped_coverage(subtable, offset, glyph_map)));
+                    push_u16(&mut table, 0);
+                    position += 2;
+                }
+                lookup_count
+            };
+            table.extend_from_slice(subtable.get(position..position + lookup_count * 4)?);
+        }
+        _ => return None,
+    }
+    append_children(&mut table, children)?;
+    Some(table)
+}
+
+// A rule set: a count and offsets to rules, each read by `rule`, which drops the rules it
+// returns None for.
+fn rule_set(
+    data: &[u8],
+    offset: usize,
+    rule: impl Fn(usize) -> Option<Vec<u8>>,
+) -> Option<Vec<u8>> {
+    let rule_count = u16_at(data, offset).unwrap_or(0) as usize;
+    let rules: Vec<Vec<u8>> = (0..rule_count)
+        .filter_map(|index| u16_at(data, offset + 2 + index * 2))
+        .filter_map(|rule_offset| rule(offset + rule_offset as usize))
+        .collect();
+    let mut table = vec![];
+    push_u16(&mut table, rules.len() as u16);
+    table.resize(2 + rules.len() * 2, 0);
+    let fields = (0..rules.len()).map(|index| 2 + index * 2);
+    append_children(&mut table, fields.zip(rules).collect())?;
+    Some(table)
+}
+
+// A (chained) sequence rule. Glyph rules hold glyph ids, which are mapped with `glyph_map`,
+// class rules hold classes, which are copied. None if one of its glyphs isn't kept.
+fn sequence_rule(
+    data: &[u8],
+    offset: usize,
+    chained: bool,
+    glyph_map: Option<&GlyphMap>,
+) -> Option<Vec<u8>> {
+    let map = |value: u16| match glyph_map {
+        Some(glyph_map) => glyph_map.get(value),
+        None => Some(value),
+    };
+    let mut rule = vec![];
+    let mut position = offset;
+    let lookup_count = if chained {
+        // Backtrack, input and lookahead sequences. The input count includes the first glyph,
+        // which comes from the coverage.
+        for sequence in 0..3 {
+            let count = u16_at(data, position)? as usize;
+            push_u16(&mut rule, count as u16);
+            position += 2;
+            let stored = if sequence == 1 {
+                count.saturating_sub(1)
+            } else {
+                count
+            };
+            for _ in 0..stored {
+                push_u16(&mut rule, map(u16_at(data, position)?)?);
+                position += 2;
+            }
+        }
+        let lookup_count = u16_at(data, position)? as usize;
+        push_u16(&mut rule, lookup_count as u16);
+        position += 2;
+        lookup_count
+    } else {
+        let glyph_count = u16_at(data, position)? as usize;
+        let lookup_count = u16_at(data, position + 2)? as usize;
+        push_u16(&mut rule, glyph_count as u16);
+        push_u16(&mut rule, lookup_count as u16);
+        position += 4;
+        for _ in 1..glyph_count {
+            push_u16(&mut rule, map(u16_at(data, position)?)?);
+            position += 2;
+        }
+        lookup_count
+    };
+    rule.extend_from_slice(data.get(position..position + lookup_count * 4)?);
+    Some(rule)
+}
+
+// GDEF with the glyph class and mark attachment class definitions and the mark glyph sets
+// rewritten for the new glyph ids. The attachment point and ligature caret lists are dropped.
+fn subset_gdef(gdef: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let with_mark_glyph_sets = u16_at(gdef, 2)? >= 2;
+    let mut table = vec![];
+    push_u16(&mut table, 1);
+    push_u16(&mut table, if with_mark_glyph_sets { 2 } else { 0 });
+    table.resize(if with_mark_glyph_sets { 14 } else { 12 }, 0);
+    let mut children = vec![];
+    // Glyph class and mark attachment class definitions.
+    for field in [4, 10].iter() {
+        let offset = u16_at(gdef, *field)? as usize;
+        if offset != 0 {
+            let classes = mapped_classes(&class_def(gdef, offset), glyph_map);
+            children.push((*field, class_def_table(&classes)));
+        }
+    }
+    let mark_glyph_sets = if with_mark_glyph_sets {
+        u16_at(gdef, 12)? as usize
+    } else {
+        0
+    };
+    if mark_glyph_sets != 0 {
+        let set_count = u16_at(gdef, mark_glyph_sets + 2)? as usize;
+        let mut sets = vec![];
+        push_u16(&mut sets, 1);
+        push_u16(&mut sets, set_count as u16);
+        sets.resize(4 + set_count * 4, 0);
+        for index in 0..set_count {
+            let coverage = u32_at(gdef, mark_glyph_sets + 4 + index * 4)? as usize;
+            let coverage = mapped_coverage(gdef, mark_glyph_sets + coverage, glyph_map);
+            let offset = sets.len() as u32;
+            sets[4 + index * 4..8 + index * 4].copy_from_slice(&offset.to_be_bytes());
+            sets.extend(coverage);
+        }
+        children.push((12, sets));
+    }
+    append_children(&mut table, children)?;
+    Some(table)
+}
+
+// Glyphs of a coverage table, in coverage index order.
+fn coverage(data: &[u8], offset: usize) -> Vec<u16> {
+    match u16_at(data, offset) {
+        Some(1) => glyph_array(data, offset + 2),
+        Some(2) => {
+            let range_count = u16_at(data, offset + 2).unwrap_or(0) as usize;
+            let mut glyphs = vec![];
+            for range in 0..range_count {
+                let record = offset + 4 + range * 6;
+                if let (Some(start), Some(end)) = (u16_at(data, record), u16_at(data, record + 2)) {
+                    glyphs.extend(start..=end);
+                }
+            }
+            glyphs
+        }
+        _ => vec![],
+    }
+}
+
+// A format 1 coverage table of sorted `glyphs`.
+fn coverage_table(glyphs: &[u16]) -> Vec<u8> {
+    let mut table = vec![];
+    push_u16(&mut table, 1);
+    push_u16(&mut table, glyphs.len() as u16);
+    for glyph in glyphs {
+        push_u16(&mut table, *glyph);
+    }
+    table
+}
+
+// The coverage table at `offset` with the kept glyphs in their new ids.
+fn mapped_coverage(data: &[u8], offset: usize, glyph_map: &GlyphMap) -> Vec<u8> {
+    let glyphs: Vec<u16> = coverage(data, offset)
+        .into_iter()
+        .filter_map(|glyph| glyph_map.get(glyph))
+        .collect();
+    coverage_table(&glyphs)
+}
+
+// (glyph, class) pairs of a class definition table, for the glyphs with a class other than 0.
+fn class_def(data: &[u8], offset: usize) -> Vec<(u16, u16)> {
+    let mut classes = vec![];
+    match u16_at(data, offset) {
+        Some(1) => {
+            let start = u16_at(data, offset + 2).unwrap_or(0) as usize;
+            let glyph_count = u16_at(data, offset + 4).unwrap_or(0) as usize;
+            for index in 0..glyph_count {
+                if let Some(class) = u16_at(data, offset + 6 + index * 2) {
+                    classes.push(((start + index) as u16, class));
+                }
+            }
+        }
+        Some(2) => {
+            let range_count = u16_at(data, offset + 2).unwrap_or(0) as usize;
+            for range in 0..range_count {
+                let record = offset + 4 + range * 6;
+                if let (Some(start), Some(end), Some(class)) = (
+                    u16_at(data, record),
+                    u16_at(data, record + 2),
+                    u16_at(data, record + 4),
+                ) {
+                    classes.extend((start..=end).map(|glyph| (glyph, class)));
+                }
+            }
+        }
+        _ => {}
+    }
+    classes.retain(|(_, class)| *class != 0);
+    classes
+}
+
+fn mapped_classes(classes: &[(u16, u16)], glyph_map: &GlyphMap) -> Vec<(u16, u16)> {
+    let mut mapped: Vec<(u16, u16)> = classes
+        .iter()
+        .filter_map(|(glyph, class)| Some((glyph_map.get(*glyph)?, *class)))
+        .collect();
+    mapped.sort_unstable();
+    mapped
+}
+
+// A format 2 class definition table of `classes` sorted by glyph.
+fn class_def_table(classes: &[(u16, u16)]) -> Vec<u8> {
+    let mut ranges: Vec<(u16, u16, u16)> = vec![];
+    for (glyph, class) in classes.iter() {
+        match ranges.last_mut() {
+            Some((_, end, range_class)) if *end + 1 == *glyph && *range_class == *class => {
+                *end = *glyph
+            }
+            _ => ranges.push((*glyph, *glyph, *class)),
+        }
+    }
+    let mut table = vec![];
+    push_u16(&mut table, 2);
+    push_u16(&mut table, ranges.len() as u16);
+    for (start, end, class) in ranges {
+        push_u16(&mut table, start);
+        push_u16(&mut table, end);
+        push_u16(&mut table, class);
+    }
+    table
+}
+
+// A u16 count followed by that many glyph ids.
+fn glyph_array(data: &[u8], offset: usize) -> Vec<u16> {
+    let count = u16_at(data, offset).unwrap_or(0) as usize;
+    (0..count)
+        .filter_map(|index| u16_at(data, offset + 2 + index * 2))
+        .collect()
+}
+
+// (glyph, first layer, layer count) of each base glyph record of a COLRv0 table.
+fn colr_base_glyphs(colr: &[u8]) -> Vec<(u16, usize, usize)> {
+    let base_glyph_count = u16_at(colr, 2).unwrap_or(0) as usize;
+    let base_glyphs = u32_at(colr, 4).unwrap_or(0) as usize;
+    let mut records = vec![];
+    for index in 0..base_glyph_count {
+        let record = base_glyphs + index * 6;
+        match (
+            u16_at(colr, record),
+            u16_at(colr, record + 2),
+            u16_at(colr, record + 4),
+        ) {
+            (Some(glyph), Some(first_layer), Some(layer_count)) => {
+                records.push((glyph, first_layer as usize, layer_count as usize))
+            }
+            _ => break,
+        }
+    }
+    records
+}
+
+fn colr_v0_closure(colr: &[u8], glyphs: &mut BTreeSet<u16>) {
+    let layers = u32_at(colr, 8).unwrap_or(0) as usize;
+    let mut added = vec![];
+    for (glyph, first_layer, layer_count) in colr_base_glyphs(colr) {
+        if glyphs.contains(&glyph) {
+            added.extend(
+                (first_layer..first_layer + layer_count)
+                    .filter_map(|layer| u16_at(colr, layers + layer * 4)),
+            );
+        }
+    }
+    glyphs.extend(added);
+}
+
+// A COLRv0 table with the base glyph and layer records of the kept glyphs.
+fn subset_colr(colr: &[u8], glyph_map: &GlyphMap) -> Vec<u8> {
+    let layers = u32_at(colr, 8).unwrap_or(0) as usize;
+    let mut base_records = vec![];
+    let mut layer_records = vec![];
+    for (glyph, first_layer, layer_count) in colr_base_glyphs(colr) {
+        let glyph = match glyph_map.get(glyph) {
+            Some(glyph) => glyph,
+            None => continue,
+        };
+        push_u16(&mut base_records, glyph);
+        push_u16(&mut base_records, (layer_records.len() / 4) as u16);
+        push_u16(&mut base_records, layer_count as u16);
+        for layer in first_layer..first_layer + layer_count {
+            let layer_glyph =
+                u16_at(colr, layers + layer * 4).and_then(|glyph| glyph_map.get(glyph));
+            push_u16(&mut layer_records, layer_glyph.unwrap_or(0));
+            push_u16(
+                &mut layer_records,
+                u16_at(colr, layers + layer * 4 + 2).unwrap_or(0),
+            );
+        }
+    }
+    let mut table = vec![];
+    push_u16(&mut table, 0);
+    push_u16(&mut table, (base_records.len() / 6) as u16);
+    push_u32(&mut table, 14);
+    push_u32(&mut table, 14 + base_records.len() as u32);
+    push_u16(&mut table, (layer_records.len() / 4) as u16);
+    table.extend(base_records);
+    table.extend(layer_records);
+    table
+}
+
+// A glyph image in CBDT/EBDT. Images of index formats 2 and 5 share their size and metrics,
+// which are kept with the image to write them to the new index subtable.
+struct Bitmap<'a> {
+    image_format: u16,
+    data: &'a [u8],
+    // The image size and big glyph metrics of the index subtable.
+    shared_metrics: Option<&'a [u8]>,
+}
+
+// The glyph images of a CBLC/EBLC strike, by glyph id.
+fn strike_bitmaps<'a>(
+    location: &'a [u8],
+    data: &'a [u8],
+    strike: usize,
+) -> Result<Vec<(u16, Bitmap<'a>)>, String> {
+    let truncated = || format!("bitmap strike {} is truncated", strike);
+    let record = 8 + strike * 48;
+    let array = u32_at(location, record).ok_or_else(truncated)? as usize;
+    let subtable_count = u32_at(location, record + 8).ok_or_else(truncated)? as usize;
+    let mut bitmaps = vec![];
+    for subtable in 0..subtable_count {
+        let entry = array + subtable * 8;
+        let (first, last, offset) = match (
+            u16_at(location, entry),
+            u16_at(location, entry + 2),
+            u32_at(location, entry + 4),
+        ) {
+            (Some(first), Some(last), Some(offset)) => (first, last, array + offset as usize),
+            _ => return Err(truncated()),
+        };
+        let (index_format, image_format, image_data) = match (
+            u16_at(location, offset),
+            u16_at(location, offset + 2),
+            u32_at(location, offset + 4),
+        ) {
+            (Some(index_format), Some(image_format), Some(image_data)) => {
+                (index_format, image_format, image_data as usize)
+            }
+            _ => return Err(truncated()),
+        };
+        // Composite images refer to other glyphs by id.
+        if image_format == 8 || image_format == 9 {
+            return Err(format!(
+                "bitmap image format {} isn't supported",
+                image_format
+            ));
+        }
+        let body = offset + 8;
+        let mut push = |glyph: u16, start: usize, end: usize, shared_metrics: Option<&'a [u8]>| {
+            if end > start {
+                let image = data
+                    .get(image_data + start..image_data + end)
+                    .ok_or_else(truncated)?;
+                bitmaps.push((
+                    glyph,
+                    Bitmap {
+                        image_format,
+                        data: image,
+                        shared_metrics,
+                    },
+                ));
+            }
+            Ok::<(), String>(())
+        };
+        match index_format {
+            // Variable size images with 32-bit and 16-bit offsets.
+            1 | 3 => {
+                for (index, glyph) in (first..=last).enumerate() {
+                    let (start, end) = if index_format == 1 {
+                        let start = u32_at(location, body + index * 4);
+                        let end = u32_at(location, body + index * 4 + 4);
+                        (
+                            start.map(|start| start as usize),
+                            end.map(|end| end as usize),
+                        )
+                    } else {
+                        let start = u16_at(location, body + index * 2);
+                        let end = u16_at(location, body + index * 2 + 2);
+                        (
+                            start.map(|start| start as usize),
+                            end.map(|end| end as usize),
+                        )
+                    };
+                    match (start, end) {
+                        (Some(start), Some(end)) => push(glyph, start, end, None)?,
+                        _ => return Err(truncated()),
+                    }
+                }
+            }
+            // Constant size images for a range of glyphs.
+            2 => {
+                let image_size = u32_at(location, body).ok_or_else(truncated)? as usize;
+                let metrics = location.get(body..body + 12).ok_or_else(truncated)?;
+                for (index, glyph) in (first..=last).enumerate() {
+                    push(
+                        glyph,
+                        index * image_size,
+                        (index + 1) * image_size,
+                        Some(metrics),
+                    )?;
+                }
+            }
+            // Variable size images for listed glyphs.
+            4 => {
+                let glyph_count = u32_at(location, body).ok_or_else(truncated)? as usize;
+                for index in 0..glyph_count {
+                    let pair = body + 4 + index * 4;
+                    match (
+                        u16_at(location, pair),
+                        u16_at(location, pair + 2),
+                        u16_at(location, pair + 6),
+                    ) {
+                        (Some(glyph), Some(start), Some(end)) => {
+                            push(glyph, start as usize, end as usize, None)?
+                        }
+                        _ => return Err(truncated()),
+                    }
+                }
+            }
+            // Constant size images for listed glyphs.
+            5 => {
+                let image_size = u32_at(location, body).ok_or_else(truncated)? as usize;
+                let metrics = location.get(body..body + 12).ok_or_else(truncated)?;
+                let glyph_count = u32_at(location, body + 12).ok_or_else(truncated)? as usize;
+                for index in 0..glyph_count {
+                    let glyph = u16_at(location, body + 16 + index * 2).ok_or_else(truncated)?;
+                    push(
+                        glyph,
+                        index * image_size,
+                        (index + 1) * image_size,
+                        Some(metrics),
+                    )?;
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "bitmap index subtable format {} isn't supported",
+                    index_format
+                ))
+            }
+        }
+    }
+    Ok(bitmaps)
+}
+
+// CBLC/CBDT or EBLC/EBDT with the images of the kept glyphs. Each strike gets an index subtable
+// per run of consecutive glyphs, in format 2 for images that share their metrics and in format
+// 1 for the others. Strikes without kept glyphs are dropped.
+fn subset_bitmaps(
+    location: &[u8],
+    data: &[u8],
+    glyph_map: &GlyphMap,
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let strike_count = u32_at(location, 4).unwrap_or(0) as usize;
+    let mut new_data = data.get(0..4).unwrap_or(&[0, 3, 0, 0]).to_vec();
+    let mut strikes = vec![];
+    for strike in 0..strike_count {
+        let mut bitmaps: Vec<(u16, Bitmap)> = strike_bitmaps(location, data, strike)?
+            .into_iter()
+            .filter_map(|(glyph, bitmap)| Some((glyph_map.get(glyph)?, bitmap)))
+            .collect();
+        if bitmaps.is_empty() {
+            continue;
+        }
+        bitmaps.sort_by_key(|(glyph, _)| *glyph);
+        let mut runs: Vec<Vec<(u16, Bitmap)>> = vec![];
+        for (glyph, bitmap) in bitmaps {
+            let continues_run = match runs.last().and_then(|run| run.last()) {
+                Some((last, last_bitmap)) => {
+                    *last + 1 == glyph
+                        && last_bitmap.image_format == bitmap.image_format
+                        && last_bitmap.shared_metrics == bitmap.shared_metrics
+                }
+                None => false,
+            };
+            match runs.last_mut() {
+                Some(run) if continues_run => run.push((glyph, bitmap)),
+                _ => runs.push(vec![(glyph, bitmap)]),
+            }
+        }
+
+        let mut array = vec![];
+        let mut subtables = vec![];
+        for run in runs.iter() {
+            push_u16(&mut array, run[0].0);
+            push_u16(&mut array, run[run.len() - 1].0);
+            push_u32(&mut array, (runs.len() * 8 + subtables.len()) as u32);
+            let image_data = new_data.len();
+            match run[0].1.shared_metrics {
+                Some(metrics) => {
+                    push_u16(&mut subtables, 2);
+                    push_u16(&mut subtables, run[0].1.image_format);
+                    push_u32(&mut subtables, image_data as u32);
+                    subtables.extend_from_slice(metrics);
+                    for (_, bitmap) in run.iter() {
+                        new_data.extend_from_slice(bitmap.data);
+                    }
+                }
+                None => {
+                    push_u16(&mut subtables, 1);
+                    push_u16(&mut subtables, run[0].1.image_format);
+                    push_u32(&mut subtables, image_data as u32);
+                    for (_, bitmap) in run.iter() {
+                        push_u32(&mut subtables, (new_data.len() - image_data) as u32);
+                        new_data.extend_from_slice(bitmap.data);
+                    }
+                    push_u32(&mut subtables, (new_data.len() - image_data) as u32);
+                }
+            }
+        }
+        let mut record = location[8 + strike * 48..8 + strike * 48 + 48].to_vec();
+        record[4..8].copy_from_slice(&((array.len() + subtables.len()) as u32).to_be_bytes());
+        record[8..12].copy_from_slice(&(runs.len() as u32).to_be_bytes());
+        let start_glyph = runs[0][0].0;
+        let end_glyph = runs
+            .iter()
+            .flatten()
+            .last()
+            .map_or(start_glyph, |(glyph, _)| *glyph);
+        record[40..42].copy_from_slice(&start_glyph.to_be_bytes());
+        record[42..44].copy_from_slice(&end_glyph.to_be_bytes());
+        array.extend(subtables);
+        strikes.push((record, array));
+    }
+
+    let mut new_location = location[0..4].to_vec();
+    push_u32(&mut new_location, strikes.len() as u32);
+    let mut array_offset = 8 + strikes.len() * 48;
+    for (record, array) in strikes.iter_mut() {
+        record[0..4].copy_from_slice(&(array_offset as u32).to_be_bytes());
+        array_offset += array.len();
+        new_location.extend_from_slice(record);
+    }
+    for (_, array) in strikes {
+        new_location.extend(array);
+    }
+    Ok((new_location, new_data))
+}
+
+// Adds the glyphs that the 'dupe' images of kept glyphs in sbix point to.
+fn sbix_dupe_closure(sbix: &[u8], glyph_count: usize, glyphs: &mut BTreeSet<u16>) {
+    let mut added = vec![];
+    for strike in sbix_strikes(sbix) {
+        for glyph in glyphs.iter() {
+            let image = sbix_image(sbix, strike, *glyph as usize, glyph_count);
+            if image.get(4..8) == Some(&b"dupe"[..]) {
+                added.extend(u16_at(image, 8));
+            }
+        }
+    }
+    glyphs.extend(added);
+}
+
+fn sbix_strikes(sbix: &[u8]) -> Vec<usize> {
+    let strike_count = u32_at(sbix, 4).unwrap_or(0) as usize;
+    (0..strike_count)
+        .filter_map(|strike| u32_at(sbix, 8 + strike * 4))
+        .map(|offset| offset as usize)
+        .collect()
+}
+
+// The glyph record (origin, graphic type and data) of `glyph` in the strike at `strike`.
+fn sbix_image(sbix: &[u8], strike: usize, glyph: usize, glyph_count: usize) -> &[u8] {
+    if glyph >= glyph_count {
+        return &[];
+    }
+    match (
+        u32_at(sbix, strike + 4 + glyph * 4),
+        u32_at(sbix, strike + 8 + glyph * 4),
+    ) {
+        (Some(start), Some(end)) => sbix
+            .get(strike + start as usize..strike + end as usize)
+            .unwrap_or(&[]),
+        _ => &[],
+    }
+}
+
+// An sbix table with the images of the kept glyphs, 'dupe' images pointing to the new ids.
+fn subset_sbix(sbix: &[u8], glyph_count: usize, glyph_map: &GlyphMap) -> Vec<u8> {
+    let strikes = sbix_strikes(sbix);
+    let mut table = sbix.get(0..4).unwrap_or(&[0, 1, 0, 1]).to_vec();
+    push_u32(&mut table, strikes.len() as u32);
+    table.resize(8 + strikes.len() * 4, 0);
+    for (index, strike) in strikes.into_iter().enumerate() {
+        let start = table.len();
+        table[8 + index * 4..12 + index * 4].copy_from_slice(&(start as u32).to_be_bytes());
+        table.extend_from_slice(sbix.get(strike..strike + 4).unwrap_or(&[0; 4]));
+        let offsets = table.len();
+        table.resize(offsets + (glyph_map.len() + 1) * 4, 0);
+        for (new_id, glyph) in glyph_map.old_ids.iter().enumerate() {
+            let offset = (table.len() - start) as u32;
+            table[offsets + new_id * 4..offsets + new_id * 4 + 4]
+                .copy_from_slice(&offset.to_be_bytes());
+            let mut image = sbix_image(sbix, strike, *glyph as usize, glyph_count).to_vec();
+            if image.get(4..8) == Some(&b"dupe"[..]) && image.len() >= 10 {
+                let target = u16_at(&image, 8).and_then(|target| glyph_map.get(target));
+                image[8..10].copy_from_slice(&target.unwrap_or(0).to_be_bytes());
+            }
+            table.extend(image);
+        }
+        let end = (table.len() - start) as u32;
+        let last = offsets + glyph_map.len() * 4;
+        table[last..last + 4].copy_from_slice(&end.to_be_bytes());
+    }
+    table
+}
+
+fn glyph_data<'a>(glyf: &'a [u8], glyph_offsets: &[usize], glyph: u16) -> &'a [u8] {
+    let glyph = glyph as usize;
+    if glyph + 1 >= glyph_offsets.len() {
+        return &[];
+    }
+    glyf.get(glyph_offsets[glyph]..glyph_offsets[glyph + 1])
+        .unwrap_or(&[])
+}
+
+fn composite_components(glyph: &[u8]) -> Vec<u16> {
+    component_offsets(glyph)
+        .into_iter()
+        .filter_map(|offset| u16_at(glyph, offset))
+        .collect()
+}
+
+// Offsets of the glyph index of each component of a composite glyph.
+fn component_offsets(glyph: &[u8]) -> Vec<usize> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut components = vec![];
+    if i16_at(glyph, 0).unwrap_or(0) >= 0 {
+        return components;
+    }
+    let mut offset = 10;
+    while let (Some(flags), Some(_)) = (u16_at(glyph, offset), u16_at(glyph, offset + 2)) {
+        components.push(offset + 2);
+        offset += 4;
+        offset += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    components
+}
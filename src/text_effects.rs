@@ -0,0 +1,326 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use crate::text_index::utf8_to_utf16;
+use skia_safe::canvas::SaveLayerRec;
+use skia_safe::paint::{Cap, Join, Style};
+use skia_safe::textlayout::{RectHeightStyle, RectWidthStyle, TextShadow};
+use skia_safe::{
+    image_filters, BlendMode, BlurStyle, Canvas, Color, MaskFilter, Paint, Point, Rect, Shader,
+    Vector,
+};
+
+#[derive(Clone)]
+pub enum Fill {
+    None,
+    Colour(Color),
+    // Gradient or image shader, in paragraph coordinates.
+    Shader(Shader),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StrokeAlign {
+    Inside,
+    Centre,
+    Outside,
+}
+
+#[derive(Clone)]
+pub struct Stroke {
+    pub width: f32,
+    pub colour: Color,
+    pub align: StrokeAlign,
+    pub join: Join,
+    pub cap: Cap,
+    pub miter_limit: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Shadow {
+    pub offset: Vector,
+    pub blur_radius: f32,
+    pub colour: Color,
+    // Grows the glyph outlines before blurring. SkParagraph's TextShadow has no spread, so
+    // shadows with a spread are painted with a dilate image filter instead.
+    pub spread: f32,
+}
+
+// A blurred halo centred behind the glyphs. Stacking glows with growing radii gives a neon look.
+#[derive(Clone, Copy, Debug)]
+pub struct Glow {
+    pub colour: Color,
+    pub radius: f32,
+    pub spread: f32,
+}
+
+#[derive(Clone)]
+pub struct TextEffect {
+    pub fill: Fill,
+    pub stroke: Option<Stroke>,
+    pub background: Option<Paint>,
+    // Painted in order, the first one lowest, above the glows and below the fill and strokes.
+    pub shadows: Vec<Shadow>,
+    // Painted in order beneath all shadows.
+    pub glows: Vec<Glow>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pass {
+    // The nth glow of each span.
+    Glow(usize),
+    // The nth shadow of each span, as a TextShadow or, with a spread, an image filter.
+    Shadow(usize),
+    Fill,
+    Stroke(StrokeAlign),
+    // Opaque glyph coverage of the spans stroked with the alignment, used to cut their strokes.
+    Mask(StrokeAlign),
+}
+
+// Paints `text` with the effects of its spans. Skia can only stroke along the centre of the
+// outline and a span has a single foreground paint, so the text is painted in passes, each built
+// from the same spans with different paints (the layout doesn't depend on paints, so all passes
+// line up):
+// - outside strokes are twice as wide, with the glyph outlines cut out with a DstOut layer,
+// - the fill, with span backgrounds,
+// - centre strokes,
+// - inside strokes are twice as wide and cut to the glyph outlines with a DstIn layer.
+// Glows and then shadows are painted before all of these, one pass per index into the lists.
+// Spans without an effect are only painted in the fill pass, with their own style.
+pub fn paint_with_effects(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    width: f32,
+    canvas: &mut Canvas,
+    origin: Point,
+) {
+    let has_stroke = |align: StrokeAlign| {
+        text.spans.iter().any(|span| {
+            span.effect
+                .as_ref()
+                .and_then(|effect| effect.stroke.as_ref())
+                .map(|stroke| stroke.align == align)
+                .unwrap_or(false)
+        })
+    };
+    let paint_pass = |pass: Pass, canvas: &mut Canvas| {
+        let paragraph = with_pass(text, pass).layout(fonts, width);
+        paragraph.paint(canvas, origin);
+    };
+
+    let paint_cut_stroke = |align: StrokeAlign, blend_mode: BlendMode, canvas: &mut Canvas| {
+        canvas.save_layer(&SaveLayerRec::default());
+        paint_pass(Pass::Stroke(align), canvas);
+        let mut mask_paint = Paint::default();
+        mask_paint.set_blend_mode(blend_mode);
+        canvas.save_layer(&SaveLayerRec::default().paint(&mask_paint));
+        paint_pass(Pass::Mask(align), canvas);
+        canvas.restore();
+        canvas.restore();
+    };
+
+    let effects = || text.spans.iter().filter_map(|span| span.effect.as_ref());
+    let glow_passes = effects()
+        .map(|effect| effect.glows.len())
+        .max()
+        .unwrap_or(0);
+    for index in 0..glow_passes {
+        paint_pass(Pass::Glow(index), canvas);
+    }
+    let shadow_passes = effects()
+        .map(|effect| effect.shadows.len())
+        .max()
+        .unwrap_or(0);
+    for index in 0..shadow_passes {
+        paint_pass(Pass::Shadow(index), canvas);
+    }
+    if has_stroke(StrokeAlign::Outside) {
+        paint_cut_stroke(StrokeAlign::Outside, BlendMode::DstOut, canvas);
+    }
+    paint_pass(Pass::Fill, canvas);
+    if has_stroke(StrokeAlign::Centre) {
+        paint_pass(Pass::Stroke(StrokeAlign::Centre), canvas);
+    }
+    if has_stroke(StrokeAlign::Inside) {
+        paint_cut_stroke(StrokeAlign::Inside, BlendMode::DstIn, canvas);
+    }
+}
+
+fn with_pass(text: &StyledText, pass: Pass) -> StyledText {
+    let mut text = text.clone();
+    for span in text.spans.iter_mut() {
+        // Shadows of the span's own style belong to the fill pass.
+        if pass != Pass::Fill {
+            span.style.reset_shadows();
+        }
+        let effect = match &span.effect {
+            Some(effect) => effect,
+            None => {
+                if pass != Pass::Fill {
+                    span.style.set_foreground_color(transparent());
+                    span.style.set_background_color(transparent());
+                }
+                continue;
+            }
+        };
+        let foreground = match pass {
+            Pass::Glow(index) => match effect.glows.get(index) {
+                Some(glow) => glow_paint(glow),
+                None => transparent(),
+            },
+            Pass::Shadow(index) => match effect.shadows.get(index) {
+                Some(shadow) if shadow.spread > 0.0 => spread_shadow_paint(shadow),
+                Some(shadow) => {
+                    span.style.add_shadow(TextShadow::new(
+                        shadow.colour,
+                        shadow.offset,
+                        blur_sigma(shadow.blur_radius) as f64,
+                    ));
+                    transparent()
+                }
+                None => transparent(),
+            },
+            Pass::Fill => {
+                let mut paint = Paint::default();
+                paint.set_anti_alias(true);
+                match &effect.fill {
+                    Fill::None => paint.set_color(Color::TRANSPARENT),
+                    Fill::Colour(colour) => paint.set_color(*colour),
+                    Fill::Shader(shader) => paint.set_shader(shader.clone()),
+                };
+                paint
+            }
+            Pass::Stroke(align) => match &effect.stroke {
+                Some(stroke) if stroke.align == align => stroke_paint(stroke),
+                _ => transparent(),
+            },
+            Pass::Mask(align) => match &effect.stroke {
+                Some(stroke) if stroke.align == align => {
+                    let mut paint = Paint::default();
+                    paint.set_anti_alias(true);
+                    paint.set_color(Color::BLACK);
+                    paint
+                }
+                _ => transparent(),
+            },
+        };
+        let background = match (pass, &effect.background) {
+            (Pass::Fill, Some(background)) => background.clone(),
+            _ => transparent(),
+        };
+        span.style.set_foreground_color(foreground);
+        span.style.set_background_color(background);
+    }
+    text
+}
+
+// Skia's conversion from a blur radius to a Gaussian sigma, see SkBlurMask::ConvertRadiusToSigma.
+pub fn blur_sigma(radius: f32) -> f32 {
+    if radius > 0.0 {
+        radius * 0.57735 + 0.5
+    } else {
+        0.0
+    }
+}
+
+// The area painted by `paint_with_effects()`, relative to the paragraph origin. Each span's line
+// boxes are grown by its strokes, glows and shadows (blurs reach 3 sigma), so a canvas sized to
+// these bounds doesn't clip them. Glyphs that overhang their line box are not accounted for.
+pub fn ink_bounds(text: &StyledText, fonts: &FontEnvironment, width: f32) -> Rect {
+    let paragraph = text.layout(fonts, width);
+    let full_text = text.text();
+    let mut bounds = Rect::new_empty();
+    let mut start = 0;
+    for span in text.spans.iter() {
+        let end = start + span.text.len();
+        let boxes = paragraph.get_rects_for_range(
+            utf8_to_utf16(&full_text, start)..utf8_to_utf16(&full_text, end),
+            RectHeightStyle::Max,
+            RectWidthStyle::Tight,
+        );
+        start = end;
+        for text_box in boxes {
+            let rect = text_box.rect;
+            bounds.join(rect);
+            let effect = match &span.effect {
+                Some(effect) => effect,
+                None => continue,
+            };
+            if let Some(stroke) = &effect.stroke {
+                let outset = match stroke.align {
+                    StrokeAlign::Inside => 0.0,
+                    StrokeAlign::Centre => stroke.width / 2.0,
+                    StrokeAlign::Outside => stroke.width,
+                };
+                bounds.join(rect.with_outset((outset, outset)));
+            }
+            for glow in effect.glows.iter() {
+                let outset = glow.spread + 3.0 * blur_sigma(glow.radius);
+                bounds.join(rect.with_outset((outset, outset)));
+            }
+            for shadow in effect.shadows.iter() {
+                let outset = shadow.spread.max(0.0) + 3.0 * blur_sigma(shadow.blur_radius);
+                bounds.join(
+                    rect.with_outset((outset, outset))
+                        .with_offset(shadow.offset),
+                );
+            }
+        }
+    }
+    bounds
+}
+
+fn glow_paint(glow: &Glow) -> Paint {
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(glow.colour);
+    if glow.spread > 0.0 {
+        // Fill and stroke together grow the outline by half the stroke width.
+        paint.set_style(Style::StrokeAndFill);
+        paint.set_stroke_width(glow.spread * 2.0);
+        paint.set_stroke_join(Join::Round);
+    }
+    paint.set_mask_filter(MaskFilter::blur(
+        BlurStyle::Normal,
+        blur_sigma(glow.radius),
+        None,
+    ));
+    paint
+}
+
+fn spread_shadow_paint(shadow: &Shadow) -> Paint {
+    let sigma = blur_sigma(shadow.blur_radius);
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    // Only the glyph coverage matters, the filter paints it in the shadow colour.
+    paint.set_color(Color::BLACK);
+    paint.set_image_filter(image_filters::drop_shadow_only(
+        shadow.offset,
+        (sigma, sigma),
+        shadow.colour,
+        image_filters::dilate((shadow.spread, shadow.spread), None, None),
+        None,
+    ));
+    paint
+}
+
+fn stroke_paint(stroke: &Stroke) -> Paint {
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(stroke.colour);
+    paint.set_style(Style::Stroke);
+    // Half of a centred stroke lies inside the outline, so inside and outside strokes are drawn
+    // at double width and one half is hidden.
+    paint.set_stroke_width(match stroke.align {
+        StrokeAlign::Centre => stroke.width,
+        StrokeAlign::Inside | StrokeAlign::Outside => stroke.width * 2.0,
+    });
+    paint.set_stroke_join(stroke.join);
+    paint.set_stroke_cap(stroke.cap);
+    paint.set_stroke_miter(stroke.miter_limit);
+    paint
+}
+
+fn transparent() -> Paint {
+    let mut paint = Paint::default();
+    paint.set_color(Color::TRANSPARENT);
+    paint
+}
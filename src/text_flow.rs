@@ -0,0 +1,88 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use skia_safe::textlayout::Paragraph;
+use skia_safe::{Canvas, Point, Rect};
+
+// The part of the text that went into one box.
+pub struct FlowedBox {
+    pub rect: Rect,
+    // UTF-8 range of the whole text laid out in this box. Empty when not even one line fit.
+    pub start: usize,
+    pub end: usize,
+    pub line_count: usize,
+    pub text: StyledText,
+    pub paragraph: Paragraph,
+}
+
+pub struct Flow {
+    pub boxes: Vec<FlowedBox>,
+    // Where the text that fit in none of the boxes starts.
+    pub overflow: Option<usize>,
+}
+
+impl Flow {
+    pub fn paint(&self, canvas: &mut Canvas) {
+        for flowed in self.boxes.iter() {
+            flowed
+                .paragraph
+                .paint(canvas, Point::new(flowed.rect.left, flowed.rect.top));
+        }
+    }
+}
+
+// Flows `text` through `boxes` in order, like linked text frames: each box takes the lines that
+// fit its height and the next box continues with the text after them. A line is never split
+// between boxes, and each box is laid out as a paragraph of its own, so a box starting in the
+// middle of a paragraph starts it without indentation or first-line styles.
+pub fn flow(text: &StyledText, fonts: &FontEnvironment, boxes: &[Rect]) -> Flow {
+    let text_length = text.text().len();
+    let mut start = 0;
+    let mut flowed_boxes = Vec::with_capacity(boxes.len());
+    for rect in boxes.iter() {
+        let remaining = text.slice(start..text_length);
+        let paragraph = remaining.layout(fonts, rect.width());
+        let mut bottom = 0.0;
+        let mut end = 0;
+        let mut line_count = 0;
+        for line in paragraph.get_line_metrics().iter() {
+            // Lines are stacked without gaps, so their heights add up to their bottom.
+            bottom += line.height as f32;
+            if bottom > rect.height() + 0.01 {
+                break;
+            }
+            end = line.end_including_newline;
+            line_count += 1;
+        }
+        let fitting = remaining.slice(0..end);
+        let paragraph = fitting.layout(fonts, rect.width());
+        flowed_boxes.push(FlowedBox {
+            rect: *rect,
+            start,
+            end: start + end,
+            line_count,
+            text: fitting,
+            paragraph,
+        });
+        start += end;
+    }
+    Flow {
+        boxes: flowed_boxes,
+        overflow: if start < text_length {
+            Some(start)
+        } else {
+            None
+        },
+    }
+}
+
+// `count` columns of equal width across `area`, `gap` apart.
+pub fn columns(area: Rect, count: usize, gap: f32) -> Vec<Rect> {
+    let count = count.max(1);
+    let width = (area.width() - gap * (count - 1) as f32) / count as f32;
+    (0..count)
+        .map(|column| {
+            let left = area.left + column as f32 * (width + gap);
+            Rect::from_xywh(left, area.top, width, area.height())
+        })
+        .collect()
+}
@@ -0,0 +1,28 @@
+// SkParagraph reports line metrics in UTF-8 offsets, but get_rects_for_range(),
+// get_word_boundary() and get_glyph_position_at_coordinate() work with UTF-16 offsets.
+
+pub fn utf8_to_utf16(text: &str, utf8_index: usize) -> usize {
+    text[..floor_char_boundary(text, utf8_index)]
+        .chars()
+        .map(char::len_utf16)
+        .sum()
+}
+
+pub fn utf16_to_utf8(text: &str, utf16_index: usize) -> usize {
+    let mut utf16_offset = 0;
+    for (utf8_offset, character) in text.char_indices() {
+        if utf16_offset >= utf16_index {
+            return utf8_offset;
+        }
+        utf16_offset += character.len_utf16();
+    }
+    text.len()
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -0,0 +1,473 @@
+use crate::emoji::clusters;
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use crate::text_index::utf8_to_utf16;
+use skia_safe::shaper::run_handler::{Buffer, RunInfo};
+use skia_safe::shaper::RunHandler;
+use skia_safe::textlayout::{Paragraph, RectHeightStyle, RectWidthStyle, TextDirection};
+use skia_safe::{
+    Canvas, Color, ContourMeasure, ContourMeasureIter, Font, FontMgr, GlyphId, Paint, Path, Point,
+    RSXform, Rect, Shaper, TextBlob, Vector,
+};
+use std::ops::Range;
+
+// Which part of the text sits at `start_offset`, as SVG's text-anchor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathAlign {
+    Start,
+    Centre,
+    End,
+}
+
+// Left keeps the path's direction, with glyphs standing on the left of it (above a path drawn
+// left to right). Right walks the path backwards, so glyphs stand on its other side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSide {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathOverflow {
+    // Glyphs whose centre falls off either end of the path are not drawn.
+    Hide,
+    // Glyphs off the ends continue along the tangent of the nearest end.
+    Extend,
+    // Glyphs off the ends wrap around, for closed paths.
+    Wrap,
+    // The text is scaled down until it fits the path.
+    Shrink,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PathTextOptions {
+    // Distance along the path, after choosing the side.
+    pub start_offset: f32,
+    pub align: PathAlign,
+    pub side: PathSide,
+    pub overflow: PathOverflow,
+    // Moves the baseline away from the path, towards the glyph tops for positive values.
+    pub baseline_offset: f32,
+}
+
+impl Default for PathTextOptions {
+    fn default() -> Self {
+        PathTextOptions {
+            start_offset: 0.0,
+            align: PathAlign::Start,
+            side: PathSide::Left,
+            overflow: PathOverflow::Hide,
+            baseline_offset: 0.0,
+        }
+    }
+}
+
+pub struct PathText {
+    pub blobs: Vec<(TextBlob, Paint)>,
+    pub glyphs_placed: usize,
+    pub glyphs_hidden: usize,
+    // Clusters none of the span's registered fonts can render.
+    pub clusters_missing: usize,
+    // Below 1 when the text was shrunk to fit.
+    pub scale: f32,
+    pub path_length: f32,
+    pub text_advance: f32,
+}
+
+impl PathText {
+    pub fn paint(&self, canvas: &mut Canvas) {
+        for (blob, paint) in self.blobs.iter() {
+            canvas.draw_text_blob(blob, Point::new(0.0, 0.0), paint);
+        }
+    }
+}
+
+pub struct Glyph {
+    pub id: GlyphId,
+    // Origin in the paragraph and advance.
+    pub x: f32,
+    pub baseline: f32,
+    pub advance: f32,
+    // Vertical offset from the baseline given by the shaper, e.g. for marks stacked on a base.
+    pub y_offset: f32,
+    pub line: usize,
+    // UTF-8 range of the glyph's cluster in the whole text.
+    pub cluster: Range<usize>,
+    // Span and index of the font family within it, glyphs are batched into runs by these.
+    pub run: (usize, usize),
+    pub font: Font,
+}
+
+// Lays `text` out on a single line and moves each glyph onto `path`, rotated to the tangent at
+// the glyph's centre.
+pub fn layout_on_path(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    path: &Path,
+    options: &PathTextOptions,
+) -> PathText {
+    let (glyphs, clusters_missing) = line_glyphs(text, fonts);
+
+    let track = Track::new(path);
+    let line_start = glyphs.first().map(|glyph| glyph.x).unwrap_or(0.0);
+    let text_advance = glyphs
+        .last()
+        .map(|glyph| glyph.x + glyph.advance - line_start)
+        .unwrap_or(0.0);
+    let scale = match options.overflow {
+        PathOverflow::Shrink if text_advance > track.length && text_advance > 0.0 => {
+            track.length / text_advance
+        }
+        _ => 1.0,
+    };
+    let anchor = match options.align {
+        PathAlign::Start => 0.0,
+        PathAlign::Centre => text_advance * scale / 2.0,
+        PathAlign::End => text_advance * scale,
+    };
+    let mut start = options.start_offset - anchor;
+    if options.overflow == PathOverflow::Shrink {
+        // Keep the shrunk text on the path whatever the alignment.
+        start = start.max(0.0).min(track.length - text_advance * scale);
+    }
+
+    let mut runs: Vec<((usize, usize), Font, Vec<GlyphId>, Vec<RSXform>)> = vec![];
+    let mut glyphs_placed = 0;
+    let mut glyphs_hidden = 0;
+    for glyph in glyphs.iter() {
+        let advance = glyph.advance * scale;
+        let mut centre = start + (glyph.x - line_start) * scale + advance / 2.0;
+        if options.overflow == PathOverflow::Wrap && track.length > 0.0 {
+            centre = centre.rem_euclid(track.length);
+        }
+        if options.overflow == PathOverflow::Hide && (centre < 0.0 || centre > track.length) {
+            glyphs_hidden += 1;
+            continue;
+        }
+        let (position, tangent) = match options.side {
+            PathSide::Left => track.pos_tan(centre),
+            PathSide::Right => {
+                let (position, tangent) = track.pos_tan(track.length - centre);
+                (position, -tangent)
+            }
+        };
+        let (cos, sin) = (tangent.x * scale, tangent.y * scale);
+        // The glyph origin is half an advance back along the tangent and the baseline offset
+        // up along the normal, in glyph space before scaling.
+        let (dx, dy) = (
+            -glyph.advance / 2.0,
+            glyph.y_offset - options.baseline_offset / scale,
+        );
+        let xform = RSXform::new(
+            cos,
+            sin,
+            (
+                position.x + cos * dx - sin * dy,
+                position.y + sin * dx + cos * dy,
+            ),
+        );
+        match runs.last_mut() {
+            Some((run, _, ids, xforms)) if *run == glyph.run => {
+                ids.push(glyph.id);
+                xforms.push(xform);
+            }
+            _ => runs.push((glyph.run, glyph.font.clone(), vec![glyph.id], vec![xform])),
+        }
+        glyphs_placed += 1;
+    }
+
+    let blobs = runs
+        .into_iter()
+        .filter_map(|((span, _), font, ids, xforms)| {
+            let blob = TextBlob::from_rsxform(&ids[..], &xforms, &font)?;
+            Some((blob, span_paint(text, span)))
+        })
+        .collect();
+    PathText {
+        blobs,
+        glyphs_placed,
+        glyphs_hidden,
+        clusters_missing,
+        scale,
+        path_length: track.length,
+        text_advance,
+    }
+}
+
+// The glyphs of `text` laid out on a single line, in visual order, and the number of clusters
+// none of the span's registered fonts can render. Newlines are treated as spaces.
+pub fn line_glyphs(text: &StyledText, fonts: &FontEnvironment) -> (Vec<Glyph>, usize) {
+    let mut text = text.clone();
+    for span in text.spans.iter_mut() {
+        span.text = span.text.replace('\n', " ");
+    }
+    let paragraph = text.layout(fonts, 1_000_000_f32);
+    paragraph_glyphs(&text, &paragraph, fonts)
+}
+
+// The glyphs of `paragraph`, laid out from `text`, in visual order line by line. Cluster positions
+// come from the paragraph, so letter spacing, kerning, bidi and font fallback between spans match
+// its layout. The glyphs within the clusters are shaped with SkShaper in the first registered
+// font of the span that covers the cluster, over runs of neighbouring clusters on a line that
+// take the same font, so ligatures, contextual forms and emoji ZWJ sequences come out as in the
+// paragraph. The style's font features aren't passed to the shaper.
+pub fn paragraph_glyphs(
+    text: &StyledText,
+    paragraph: &Paragraph,
+    fonts: &FontEnvironment,
+) -> (Vec<Glyph>, usize) {
+    let full_text = text.text();
+    let lines = paragraph.get_line_metrics();
+    let left_to_right = text.paragraph_style.text_direction() == TextDirection::LTR;
+    // Clusters are only shaped in a font that has all their characters, so fallback never runs.
+    let shaper = Shaper::new(FontMgr::default());
+    let mut glyphs = vec![];
+    let mut clusters_missing = 0;
+    let mut span_start = 0;
+    for (span_index, span) in text.spans.iter().enumerate() {
+        if span.placeholder.is_some() {
+            span_start += span.text.len();
+            continue;
+        }
+        // (line, family, font, clusters) of each run shaped at once.
+        let mut runs: Vec<(usize, usize, Font, Vec<(Range<usize>, Rect)>)> = vec![];
+        for (range, cluster_box) in layout_clusters(paragraph, &full_text, span_start, &span.text) {
+            let characters = without_joiners(&span.text[range.clone()]);
+            let (family, font) =
+                match cluster_font(span.style.font_families().iter(), &characters, fonts) {
+                    Some((family, font)) => {
+                        (family, font.with_size(span.style.font_size()).unwrap())
+                    }
+                    None => {
+                        clusters_missing += 1;
+                        continue;
+                    }
+                };
+            let line = lines
+                .iter()
+                .position(|line| span_start + range.start < line.end_including_newline)
+                .unwrap_or_else(|| lines.len().saturating_sub(1));
+            match runs.last_mut() {
+                Some((run_line, run_family, _, clusters))
+                    if *run_line == line && *run_family == family =>
+                {
+                    clusters.push((range, cluster_box))
+                }
+                _ => runs.push((line, family, font, vec![(range, cluster_box)])),
+            }
+        }
+
+        for (line, family, font, clusters) in runs {
+            let baseline = lines
+                .get(line)
+                .map(|line| line.baseline as f32)
+                .unwrap_or(0.0);
+            let run_start = clusters[0].0.start;
+            let run_end = clusters[clusters.len() - 1].0.end;
+            let mut shaped = ShapedRun::default();
+            shaper.shape(
+                &span.text[run_start..run_end],
+                &font,
+                left_to_right,
+                1_000_000_f32,
+                &mut shaped,
+            );
+            let mut advances = vec![0.0; shaped.ids.len()];
+            font.get_widths(&shaped.ids, &mut advances);
+            // Glyphs of characters between the clusters, hidden by the layout or without a
+            // font, belong to no cluster and are dropped.
+            for (range, cluster_box) in clusters.iter() {
+                let in_cluster: Vec<usize> = (0..shaped.ids.len())
+                    .filter(|index| range.contains(&(run_start + shaped.clusters[*index] as usize)))
+                    .collect();
+                // The cluster's glyphs keep their shaped positions relative to its left edge.
+                let left = in_cluster
+                    .iter()
+                    .map(|index| shaped.positions[*index].x)
+                    .fold(f32::INFINITY, f32::min);
+                for index in in_cluster {
+                    let position = shaped.positions[index];
+                    glyphs.push(Glyph {
+                        id: shaped.ids[index],
+                        x: cluster_box.left + position.x - left,
+                        baseline,
+                        advance: advances[index],
+                        y_offset: position.y,
+                        line,
+                        cluster: span_start + range.start..span_start + range.end,
+                        run: (span_index, family),
+                        font: font.clone(),
+                    });
+                }
+            }
+        }
+        span_start += span.text.len();
+    }
+    glyphs.sort_by(|a, b| (a.line, a.x).partial_cmp(&(b.line, b.x)).unwrap());
+    (glyphs, clusters_missing)
+}
+
+// Collects what SkShaper produces for a run of text: glyphs, their positions and the UTF-8 offsets
+// of their clusters, in visual order.
+#[derive(Default)]
+struct ShapedRun {
+    ids: Vec<GlyphId>,
+    positions: Vec<Point>,
+    clusters: Vec<u32>,
+    x: f32,
+}
+
+impl RunHandler for ShapedRun {
+    fn begin_line(&mut self) {}
+
+    fn run_info(&mut self, _info: &RunInfo) {}
+
+    fn commit_run_info(&mut self) {}
+
+    fn run_buffer(&mut self, info: &RunInfo) -> Buffer {
+        let start = self.ids.len();
+        let end = start + info.glyph_count;
+        self.ids.resize(end, 0);
+        self.positions.resize(end, Point::new(0.0, 0.0));
+        self.clusters.resize(end, 0);
+        Buffer {
+            glyphs: &mut self.ids[start..],
+            positions: &mut self.positions[start..],
+            offsets: None,
+            clusters: Some(&mut self.clusters[start..]),
+            point: Point::new(self.x, 0.0),
+        }
+    }
+
+    fn commit_run_buffer(&mut self, info: &RunInfo) {
+        self.x += info.advance.x;
+    }
+
+    fn commit_line(&mut self) {}
+}
+
+// The span's text split into the clusters the paragraph positions separately, with their boxes:
+// emoji sequences whole, other text by character, with characters that have no box of their own
+// or share the box of the one before (combining marks, ligatures) merged into its cluster.
+pub fn layout_clusters(
+    paragraph: &Paragraph,
+    full_text: &str,
+    span_start: usize,
+    span_text: &str,
+) -> Vec<(Range<usize>, Rect)> {
+    let mut layout_clusters: Vec<(Range<usize>, Rect)> = vec![];
+    for cluster in clusters(span_text) {
+        let ranges: Vec<Range<usize>> = if cluster.is_emoji {
+            vec![cluster.range.clone()]
+        } else {
+            span_text[cluster.range.clone()]
+                .char_indices()
+                .map(|(offset, c)| {
+                    let start = cluster.range.start + offset;
+                    start..start + c.len_utf8()
+                })
+                .collect()
+        };
+        for range in ranges {
+            let boxes = paragraph.get_rects_for_range(
+                utf8_to_utf16(full_text, span_start + range.start)
+                    ..utf8_to_utf16(full_text, span_start + range.end),
+                RectHeightStyle::Tight,
+                RectWidthStyle::Tight,
+            );
+            let rect = boxes.first().map(|text_box| text_box.rect);
+            let merge = match layout_clusters.last() {
+                Some((previous, previous_rect)) => {
+                    previous.end == range.start
+                        && rect.map(|rect| rect == *previous_rect).unwrap_or(true)
+                }
+                None => false,
+            };
+            if merge {
+                layout_clusters.last_mut().unwrap().0.end = range.end;
+            } else if let Some(rect) = rect {
+                layout_clusters.push((range, rect));
+            }
+            // Otherwise the character is hidden by the layout, e.g. a control character.
+        }
+    }
+    layout_clusters
+}
+
+// Joiners and variation selectors only select glyphs through substitutions, so a font doesn't
+// need glyphs for them to cover a cluster.
+fn without_joiners(cluster: &str) -> String {
+    cluster
+        .chars()
+        .filter(|c| *c != '\u{200D}' && *c != '\u{FE0E}' && *c != '\u{FE0F}')
+        .collect()
+}
+
+fn cluster_font<'a>(
+    families: impl Iterator<Item = &'a str>,
+    characters: &str,
+    fonts: &FontEnvironment,
+) -> Option<(usize, Font)> {
+    families
+        .enumerate()
+        .filter_map(|(index, family)| fonts.typeface(family).map(|typeface| (index, typeface)))
+        .map(|(index, typeface)| (index, Font::from_typeface(typeface.clone(), None)))
+        .find(|(_, font)| {
+            let mut glyph_ids = vec![0; characters.chars().count()];
+            font.str_to_glyphs(characters, &mut glyph_ids);
+            !glyph_ids.iter().any(|glyph| *glyph == 0)
+        })
+}
+
+pub fn span_paint(text: &StyledText, span: usize) -> Paint {
+    let style = &text.spans[span].style;
+    if style.has_foreground() {
+        return style.foreground();
+    }
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(style.color());
+    paint
+}
+
+// The contours of a path measured end to end.
+struct Track {
+    contours: Vec<ContourMeasure>,
+    length: f32,
+}
+
+impl Track {
+    fn new(path: &Path) -> Self {
+        let contours: Vec<ContourMeasure> = ContourMeasureIter::new(path, false, None).collect();
+        let length = contours.iter().map(|contour| contour.length()).sum();
+        Track { contours, length }
+    }
+
+    // Distances before the start or past the end continue along the end tangents.
+    fn pos_tan(&self, distance: f32) -> (Point, Vector) {
+        let mut remaining = distance;
+        for (index, contour) in self.contours.iter().enumerate() {
+            let last = index + 1 == self.contours.len();
+            if remaining <= contour.length() || last {
+                let clamped = remaining.max(0.0).min(contour.length());
+                return match contour.pos_tan(clamped) {
+                    Some((position, tangent)) => {
+                        (position + tangent * (remaining - clamped), tangent)
+                    }
+                    None => (Point::new(0.0, 0.0), Vector::new(1.0, 0.0)),
+                };
+            }
+            remaining -= contour.length();
+        }
+        (Point::new(distance, 0.0), Vector::new(1.0, 0.0))
+    }
+}
+
+// Draws the path itself, for checking glyph placement.
+pub fn path_guide_paint() -> Paint {
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_style(skia_safe::paint::Style::Stroke);
+    paint.set_color(Color::from_argb(96, 255, 0, 0));
+    paint
+}
@@ -0,0 +1,165 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::{Span, StyledText};
+use crate::text_index::{utf16_to_utf8, utf8_to_utf16};
+use skia_safe::textlayout::{
+    Paragraph, ParagraphStyle, RectHeightStyle, RectWidthStyle, TextDirection,
+};
+use skia_safe::Point;
+
+pub struct Truncation {
+    pub max_lines: usize,
+    pub ellipsis: String,
+}
+
+pub struct TruncationReport {
+    pub truncated: bool,
+    // UTF-8 offset into the text just past the last character that is still painted. Equal to
+    // the text length when nothing was cut off.
+    pub last_visible_index: usize,
+    pub line_count: usize,
+    pub ellipsis_width: f32,
+}
+
+pub struct TruncatedParagraph {
+    pub paragraph: Paragraph,
+    pub text: StyledText,
+    pub report: TruncationReport,
+}
+
+// Lays out `text` limited to `truncation.max_lines` lines, ending the last line with the ellipsis.
+//
+// SkParagraph shapes the ellipsis with the paragraph's default text style, so the text is laid
+// out twice: once to find the span the cut falls into, and again with that span's style as the
+// default so the ellipsis looks like the text it replaces.
+pub fn layout_truncated(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    width: f32,
+    truncation: &Truncation,
+) -> TruncatedParagraph {
+    // A trailing newline only adds an empty last line. It must not count as text that got cut
+    // off, or a paragraph that fits exactly into max_lines would get an ellipsis.
+    let text = trim_trailing_newlines(text);
+    let full_text = text.text();
+
+    let mut truncated_text = with_truncation(&text, &text.paragraph_style, truncation);
+    let paragraph = truncated_text.layout(fonts, width);
+    let last_visible_index = if paragraph.did_exceed_max_lines() {
+        visible_end(&paragraph, &full_text, 0.0)
+    } else {
+        full_text.len()
+    };
+
+    if let Some(span) = span_at(&text, last_visible_index) {
+        let mut paragraph_style = text.paragraph_style.clone();
+        paragraph_style.set_text_style(&span.style);
+        truncated_text = with_truncation(&text, &paragraph_style, truncation);
+    }
+    let paragraph = truncated_text.layout(fonts, width);
+    let truncated = paragraph.did_exceed_max_lines();
+    let ellipsis_width = if truncated {
+        measure_ellipsis(&truncated_text, fonts, &truncation.ellipsis)
+    } else {
+        0.0
+    };
+    let report = TruncationReport {
+        truncated,
+        last_visible_index: if truncated {
+            visible_end(&paragraph, &full_text, ellipsis_width)
+        } else {
+            full_text.len()
+        },
+        line_count: paragraph.line_number(),
+        ellipsis_width,
+    };
+    TruncatedParagraph {
+        paragraph,
+        text: truncated_text,
+        report,
+    }
+}
+
+fn with_truncation(
+    text: &StyledText,
+    paragraph_style: &ParagraphStyle,
+    truncation: &Truncation,
+) -> StyledText {
+    let mut paragraph_style = paragraph_style.clone();
+    paragraph_style.set_max_lines(truncation.max_lines);
+    paragraph_style.set_ellipsis(&truncation.ellipsis);
+    StyledText {
+        paragraph_style,
+        spans: text.spans.clone(),
+    }
+}
+
+fn trim_trailing_newlines(text: &StyledText) -> StyledText {
+    let mut trimmed = text.clone();
+    while let Some(span) = trimmed.spans.last_mut() {
+        let length = span.text.trim_end_matches('\n').len();
+        span.text.truncate(length);
+        if !span.text.is_empty() {
+            break;
+        }
+        trimmed.spans.pop();
+    }
+    trimmed
+}
+
+// The span containing the character just before `index`, i.e. the one the ellipsis replaces.
+fn span_at(text: &StyledText, index: usize) -> Option<&Span> {
+    let mut span_start = 0;
+    for span in text.spans.iter() {
+        let span_end = span_start + span.text.len();
+        if index > span_start && index <= span_end {
+            return Some(span);
+        }
+        span_start = span_end;
+    }
+    text.spans.first()
+}
+
+fn measure_ellipsis(text: &StyledText, fonts: &FontEnvironment, ellipsis: &str) -> f32 {
+    let mut paragraph_style = text.paragraph_style.clone();
+    paragraph_style.set_max_lines(None);
+    let mut ellipsis_text = StyledText::new(paragraph_style.clone());
+    ellipsis_text.add_span(ellipsis, paragraph_style.text_style().clone());
+    ellipsis_text
+        .layout(fonts, 1_000_000_f32)
+        .max_intrinsic_width()
+}
+
+// Hit-tests the last visible line just inside the edge where the ellipsis starts. Positions
+// returned by hit testing always fall on cluster boundaries, so an emoji sequence is never
+// reported as partially visible.
+fn visible_end(paragraph: &Paragraph, text: &str, ellipsis_width: f32) -> usize {
+    let line_metrics = paragraph.get_line_metrics();
+    let last_line = match line_metrics.last() {
+        Some(line) => line,
+        None => return 0,
+    };
+    let left = last_line.left as f32;
+    let right = (last_line.left + last_line.width) as f32;
+    let x = match paragraph_direction(paragraph, last_line.start_index, text) {
+        // In right-to-left text the logical end of the line, and so the ellipsis, is on the left.
+        TextDirection::RTL => (left + ellipsis_width + 0.01).min(right),
+        TextDirection::LTR => (right - ellipsis_width - 0.01).max(left),
+    };
+    let position =
+        paragraph.get_glyph_position_at_coordinate(Point::new(x, last_line.baseline as f32));
+    let index = utf16_to_utf8(text, position.position.max(0) as usize);
+    index.max(last_line.start_index).min(text.len())
+}
+
+fn paragraph_direction(paragraph: &Paragraph, line_start: usize, text: &str) -> TextDirection {
+    let line_start = utf8_to_utf16(text, line_start);
+    paragraph
+        .get_rects_for_range(
+            line_start..line_start + 1,
+            RectHeightStyle::Tight,
+            RectWidthStyle::Tight,
+        )
+        .first()
+        .map(|text_box| text_box.direct)
+        .unwrap_or(TextDirection::LTR)
+}
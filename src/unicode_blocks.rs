@@ -0,0 +1,344 @@
+// Unicode blocks (from Blocks.txt) with the script most of their characters belong to. Blocks
+// shared by many scripts, like punctuation and symbols, are listed as "Common".
+pub const BLOCKS: &[(u32, u32, &str, &str)] = &[
+    (0x0000, 0x007F, "Basic Latin", "Latin"),
+    (0x0080, 0x00FF, "Latin-1 Supplement", "Latin"),
+    (0x0100, 0x017F, "Latin Extended-A", "Latin"),
+    (0x0180, 0x024F, "Latin Extended-B", "Latin"),
+    (0x0250, 0x02AF, "IPA Extensions", "Latin"),
+    (0x02B0, 0x02FF, "Spacing Modifier Letters", "Common"),
+    (0x0300, 0x036F, "Combining Diacritical Marks", "Inherited"),
+    (0x0370, 0x03FF, "Greek and Coptic", "Greek"),
+    (0x0400, 0x04FF, "Cyrillic", "Cyrillic"),
+    (0x0500, 0x052F, "Cyrillic Supplement", "Cyrillic"),
+    (0x0530, 0x058F, "Armenian", "Armenian"),
+    (0x0590, 0x05FF, "Hebrew", "Hebrew"),
+    (0x0600, 0x06FF, "Arabic", "Arabic"),
+    (0x0700, 0x074F, "Syriac", "Syriac"),
+    (0x0750, 0x077F, "Arabic Supplement", "Arabic"),
+    (0x0780, 0x07BF, "Thaana", "Thaana"),
+    (0x07C0, 0x07FF, "NKo", "Nko"),
+    (0x0800, 0x083F, "Samaritan", "Samaritan"),
+    (0x0840, 0x085F, "Mandaic", "Mandaic"),
+    (0x0860, 0x086F, "Syriac Supplement", "Syriac"),
+    (0x0870, 0x089F, "Arabic Extended-B", "Arabic"),
+    (0x08A0, 0x08FF, "Arabic Extended-A", "Arabic"),
+    (0x0900, 0x097F, "Devanagari", "Devanagari"),
+    (0x0980, 0x09FF, "Bengali", "Bengali"),
+    (0x0A00, 0x0A7F, "Gurmukhi", "Gurmukhi"),
+    (0x0A80, 0x0AFF, "Gujarati", "Gujarati"),
+    (0x0B00, 0x0B7F, "Oriya", "Oriya"),
+    (0x0B80, 0x0BFF, "Tamil", "Tamil"),
+    (0x0C00, 0x0C7F, "Telugu", "Telugu"),
+    (0x0C80, 0x0CFF, "Kannada", "Kannada"),
+    (0x0D00, 0x0D7F, "Malayalam", "Malayalam"),
+    (0x0D80, 0x0DFF, "Sinhala", "Sinhala"),
+    (0x0E00, 0x0E7F, "Thai", "Thai"),
+    (0x0E80, 0x0EFF, "Lao", "Lao"),
+    (0x0F00, 0x0FFF, "Tibetan", "Tibetan"),
+    (0x1000, 0x109F, "Myanmar", "Myanmar"),
+    (0x10A0, 0x10FF, "Georgian", "Georgian"),
+    (0x1100, 0x11FF, "Hangul Jamo", "Hangul"),
+    (0x1200, 0x137F, "Ethiopic", "Ethiopic"),
+    (0x1380, 0x139F, "Ethiopic Supplement", "Ethiopic"),
+    (0x13A0, 0x13FF, "Cherokee", "Cherokee"),
+    (
+        0x1400,
+        0x167F,
+        "Unified Canadian Aboriginal Syllabics",
+        "Canadian Aboriginal",
+    ),
+    (0x1680, 0x169F, "Ogham", "Ogham"),
+    (0x16A0, 0x16FF, "Runic", "Runic"),
+    (0x1700, 0x171F, "Tagalog", "Tagalog"),
+    (0x1720, 0x173F, "Hanunoo", "Hanunoo"),
+    (0x1740, 0x175F, "Buhid", "Buhid"),
+    (0x1760, 0x177F, "Tagbanwa", "Tagbanwa"),
+    (0x1780, 0x17FF, "Khmer", "Khmer"),
+    (0x1800, 0x18AF, "Mongolian", "Mongolian"),
+    (
+        0x18B0,
+        0x18FF,
+        "Unified Canadian Aboriginal Syllabics Extended",
+        "Canadian Aboriginal",
+    ),
+    (0x1900, 0x194F, "Limbu", "Limbu"),
+    (0x1950, 0x197F, "Tai Le", "Tai Le"),
+    (0x1980, 0x19DF, "New Tai Lue", "New Tai Lue"),
+    (0x19E0, 0x19FF, "Khmer Symbols", "Khmer"),
+    (0x1A00, 0x1A1F, "Buginese", "Buginese"),
+    (0x1A20, 0x1AAF, "Tai Tham", "Tai Tham"),
+    (
+        0x1AB0,
+        0x1AFF,
+        "Combining Diacritical Marks Extended",
+        "Inherited",
+    ),
+    (0x1B00, 0x1B7F, "Balinese", "Balinese"),
+    (0x1B80, 0x1BBF, "Sundanese", "Sundanese"),
+    (0x1BC0, 0x1BFF, "Batak", "Batak"),
+    (0x1C00, 0x1C4F, "Lepcha", "Lepcha"),
+    (0x1C50, 0x1C7F, "Ol Chiki", "Ol Chiki"),
+    (0x1C80, 0x1C8F, "Cyrillic Extended-C", "Cyrillic"),
+    (0x1C90, 0x1CBF, "Georgian Extended", "Georgian"),
+    (0x1CC0, 0x1CCF, "Sundanese Supplement", "Sundanese"),
+    (0x1CD0, 0x1CFF, "Vedic Extensions", "Inherited"),
+    (0x1D00, 0x1D7F, "Phonetic Extensions", "Latin"),
+    (0x1D80, 0x1DBF, "Phonetic Extensions Supplement", "Latin"),
+    (
+        0x1DC0,
+        0x1DFF,
+        "Combining Diacritical Marks Supplement",
+        "Inherited",
+    ),
+    (0x1E00, 0x1EFF, "Latin Extended Additional", "Latin"),
+    (0x1F00, 0x1FFF, "Greek Extended", "Greek"),
+    (0x2000, 0x206F, "General Punctuation", "Common"),
+    (0x2070, 0x209F, "Superscripts and Subscripts", "Common"),
+    (0x20A0, 0x20CF, "Currency Symbols", "Common"),
+    (
+        0x20D0,
+        0x20FF,
+        "Combining Diacritical Marks for Symbols",
+        "Inherited",
+    ),
+    (0x2100, 0x214F, "Letterlike Symbols", "Common"),
+    (0x2150, 0x218F, "Number Forms", "Common"),
+    (0x2190, 0x21FF, "Arrows", "Common"),
+    (0x2200, 0x22FF, "Mathematical Operators", "Common"),
+    (0x2300, 0x23FF, "Miscellaneous Technical", "Common"),
+    (0x2400, 0x243F, "Control Pictures", "Common"),
+    (0x2440, 0x245F, "Optical Character Recognition", "Common"),
+    (0x2460, 0x24FF, "Enclosed Alphanumerics", "Common"),
+    (0x2500, 0x257F, "Box Drawing", "Common"),
+    (0x2580, 0x259F, "Block Elements", "Common"),
+    (0x25A0, 0x25FF, "Geometric Shapes", "Common"),
+    (0x2600, 0x26FF, "Miscellaneous Symbols", "Common"),
+    (0x2700, 0x27BF, "Dingbats", "Common"),
+    (
+        0x27C0,
+        0x27EF,
+        "Miscellaneous Mathematical Symbols-A",
+        "Common",
+    ),
+    (0x27F0, 0x27FF, "Supplemental Arrows-A", "Common"),
+    (0x2800, 0x28FF, "Braille Patterns", "Braille"),
+    (0x2900, 0x297F, "Supplemental Arrows-B", "Common"),
+    (
+        0x2980,
+        0x29FF,
+        "Miscellaneous Mathematical Symbols-B",
+        "Common",
+    ),
+    (
+        0x2A00,
+        0x2AFF,
+        "Supplemental Mathematical Operators",
+        "Common",
+    ),
+    (0x2B00, 0x2BFF, "Miscellaneous Symbols and Arrows", "Common"),
+    (0x2C00, 0x2C5F, "Glagolitic", "Glagolitic"),
+    (0x2C60, 0x2C7F, "Latin Extended-C", "Latin"),
+    (0x2C80, 0x2CFF, "Coptic", "Coptic"),
+    (0x2D00, 0x2D2F, "Georgian Supplement", "Georgian"),
+    (0x2D30, 0x2D7F, "Tifinagh", "Tifinagh"),
+    (0x2D80, 0x2DDF, "Ethiopic Extended", "Ethiopic"),
+    (0x2DE0, 0x2DFF, "Cyrillic Extended-A", "Cyrillic"),
+    (0x2E00, 0x2E7F, "Supplemental Punctuation", "Common"),
+    (0x2E80, 0x2EFF, "CJK Radicals Supplement", "Han"),
+    (0x2F00, 0x2FDF, "Kangxi Radicals", "Han"),
+    (
+        0x2FF0,
+        0x2FFF,
+        "Ideographic Description Characters",
+        "Common",
+    ),
+    (0x3000, 0x303F, "CJK Symbols and Punctuation", "Common"),
+    (0x3040, 0x309F, "Hiragana", "Hiragana"),
+    (0x30A0, 0x30FF, "Katakana", "Katakana"),
+    (0x3100, 0x312F, "Bopomofo", "Bopomofo"),
+    (0x3130, 0x318F, "Hangul Compatibility Jamo", "Hangul"),
+    (0x3190, 0x319F, "Kanbun", "Common"),
+    (0x31A0, 0x31BF, "Bopomofo Extended", "Bopomofo"),
+    (0x31C0, 0x31EF, "CJK Strokes", "Common"),
+    (0x31F0, 0x31FF, "Katakana Phonetic Extensions", "Katakana"),
+    (0x3200, 0x32FF, "Enclosed CJK Letters and Months", "Common"),
+    (0x3300, 0x33FF, "CJK Compatibility", "Common"),
+    (0x3400, 0x4DBF, "CJK Unified Ideographs Extension A", "Han"),
+    (0x4DC0, 0x4DFF, "Yijing Hexagram Symbols", "Common"),
+    (0x4E00, 0x9FFF, "CJK Unified Ideographs", "Han"),
+    (0xA000, 0xA48F, "Yi Syllables", "Yi"),
+    (0xA490, 0xA4CF, "Yi Radicals", "Yi"),
+    (0xA4D0, 0xA4FF, "Lisu", "Lisu"),
+    (0xA500, 0xA63F, "Vai", "Vai"),
+    (0xA640, 0xA69F, "Cyrillic Extended-B", "Cyrillic"),
+    (0xA6A0, 0xA6FF, "Bamum", "Bamum"),
+    (0xA700, 0xA71F, "Modifier Tone Letters", "Common"),
+    (0xA720, 0xA7FF, "Latin Extended-D", "Latin"),
+    (0xA800, 0xA82F, "Syloti Nagri", "Syloti Nagri"),
+    (0xA830, 0xA83F, "Common Indic Number Forms", "Common"),
+    (0xA840, 0xA87F, "Phags-pa", "Phags Pa"),
+    (0xA880, 0xA8DF, "Saurashtra", "Saurashtra"),
+    (0xA8E0, 0xA8FF, "Devanagari Extended", "Devanagari"),
+    (0xA900, 0xA92F, "Kayah Li", "Kayah Li"),
+    (0xA930, 0xA95F, "Rejang", "Rejang"),
+    (0xA960, 0xA97F, "Hangul Jamo Extended-A", "Hangul"),
+    (0xA980, 0xA9DF, "Javanese", "Javanese"),
+    (0xA9E0, 0xA9FF, "Myanmar Extended-B", "Myanmar"),
+    (0xAA00, 0xAA5F, "Cham", "Cham"),
+    (0xAA60, 0xAA7F, "Myanmar Extended-A", "Myanmar"),
+    (0xAA80, 0xAADF, "Tai Viet", "Tai Viet"),
+    (0xAAE0, 0xAAFF, "Meetei Mayek Extensions", "Meetei Mayek"),
+    (0xAB00, 0xAB2F, "Ethiopic Extended-A", "Ethiopic"),
+    (0xAB30, 0xAB6F, "Latin Extended-E", "Latin"),
+    (0xAB70, 0xABBF, "Cherokee Supplement", "Cherokee"),
+    (0xABC0, 0xABFF, "Meetei Mayek", "Meetei Mayek"),
+    (0xAC00, 0xD7AF, "Hangul Syllables", "Hangul"),
+    (0xD7B0, 0xD7FF, "Hangul Jamo Extended-B", "Hangul"),
+    (0xE000, 0xF8FF, "Private Use Area", "Unknown"),
+    (0xF900, 0xFAFF, "CJK Compatibility Ideographs", "Han"),
+    (0xFB00, 0xFB4F, "Alphabetic Presentation Forms", "Latin"),
+    (0xFB50, 0xFDFF, "Arabic Presentation Forms-A", "Arabic"),
+    (0xFE00, 0xFE0F, "Variation Selectors", "Inherited"),
+    (0xFE10, 0xFE1F, "Vertical Forms", "Common"),
+    (0xFE20, 0xFE2F, "Combining Half Marks", "Inherited"),
+    (0xFE30, 0xFE4F, "CJK Compatibility Forms", "Common"),
+    (0xFE50, 0xFE6F, "Small Form Variants", "Common"),
+    (0xFE70, 0xFEFF, "Arabic Presentation Forms-B", "Arabic"),
+    (0xFF00, 0xFFEF, "Halfwidth and Fullwidth Forms", "Common"),
+    (0xFFF0, 0xFFFF, "Specials", "Common"),
+    (0x10000, 0x1007F, "Linear B Syllabary", "Linear B"),
+    (0x10300, 0x1032F, "Old Italic", "Old Italic"),
+    (0x10330, 0x1034F, "Gothic", "Gothic"),
+    (0x10400, 0x1044F, "Deseret", "Deseret"),
+    (0x10450, 0x1047F, "Shavian", "Shavian"),
+    (0x10480, 0x104AF, "Osmanya", "Osmanya"),
+    (0x10800, 0x1083F, "Cypriot Syllabary", "Cypriot"),
+    (0x10900, 0x1091F, "Phoenician", "Phoenician"),
+    (0x10A00, 0x10A5F, "Kharoshthi", "Kharoshthi"),
+    (0x11000, 0x1107F, "Brahmi", "Brahmi"),
+    (0x12000, 0x123FF, "Cuneiform", "Cuneiform"),
+    (
+        0x13000,
+        0x1342F,
+        "Egyptian Hieroglyphs",
+        "Egyptian Hieroglyphs",
+    ),
+    (0x16A40, 0x16A6F, "Mro", "Mro"),
+    (0x1B000, 0x1B0FF, "Kana Supplement", "Hiragana"),
+    (
+        0x1D400,
+        0x1D7FF,
+        "Mathematical Alphanumeric Symbols",
+        "Common",
+    ),
+    (0x1E900, 0x1E95F, "Adlam", "Adlam"),
+    (
+        0x1EE00,
+        0x1EEFF,
+        "Arabic Mathematical Alphabetic Symbols",
+        "Arabic",
+    ),
+    (0x1F000, 0x1F02F, "Mahjong Tiles", "Common"),
+    (0x1F030, 0x1F09F, "Domino Tiles", "Common"),
+    (0x1F0A0, 0x1F0FF, "Playing Cards", "Common"),
+    (
+        0x1F100,
+        0x1F1FF,
+        "Enclosed Alphanumeric Supplement",
+        "Common",
+    ),
+    (
+        0x1F200,
+        0x1F2FF,
+        "Enclosed Ideographic Supplement",
+        "Common",
+    ),
+    (
+        0x1F300,
+        0x1F5FF,
+        "Miscellaneous Symbols and Pictographs",
+        "Common",
+    ),
+    (0x1F600, 0x1F64F, "Emoticons", "Common"),
+    (0x1F650, 0x1F67F, "Ornamental Dingbats", "Common"),
+    (0x1F680, 0x1F6FF, "Transport and Map Symbols", "Common"),
+    (0x1F700, 0x1F77F, "Alchemical Symbols", "Common"),
+    (0x1F780, 0x1F7FF, "Geometric Shapes Extended", "Common"),
+    (0x1F800, 0x1F8FF, "Supplemental Arrows-C", "Common"),
+    (
+        0x1F900,
+        0x1F9FF,
+        "Supplemental Symbols and Pictographs",
+        "Common",
+    ),
+    (0x1FA00, 0x1FA6F, "Chess Symbols", "Common"),
+    (
+        0x1FA70,
+        0x1FAFF,
+        "Symbols and Pictographs Extended-A",
+        "Common",
+    ),
+    (0x1FB00, 0x1FBFF, "Symbols for Legacy Computing", "Common"),
+    (
+        0x20000,
+        0x2A6DF,
+        "CJK Unified Ideographs Extension B",
+        "Han",
+    ),
+    (
+        0x2A700,
+        0x2B73F,
+        "CJK Unified Ideographs Extension C",
+        "Han",
+    ),
+    (
+        0x2B740,
+        0x2B81F,
+        "CJK Unified Ideographs Extension D",
+        "Han",
+    ),
+    (
+        0x2B820,
+        0x2CEAF,
+        "CJK Unified Ideographs Extension E",
+        "Han",
+    ),
+    (
+        0x2CEB0,
+        0x2EBEF,
+        "CJK Unified Ideographs Extension F",
+        "Han",
+    ),
+    (
+        0x2F800,
+        0x2FA1F,
+        "CJK Compatibility Ideographs Supplement",
+        "Han",
+    ),
+    (
+        0x30000,
+        0x3134F,
+        "CJK Unified Ideographs Extension G",
+        "Han",
+    ),
+    (0xE0000, 0xE007F, "Tags", "Common"),
+    (
+        0xE0100,
+        0xE01EF,
+        "Variation Selectors Supplement",
+        "Inherited",
+    ),
+    (
+        0xF0000,
+        0xFFFFF,
+        "Supplementary Private Use Area-A",
+        "Unknown",
+    ),
+    (
+        0x100000,
+        0x10FFFF,
+        "Supplementary Private Use Area-B",
+        "Unknown",
+    ),
+];
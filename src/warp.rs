@@ -0,0 +1,263 @@
+use crate::fonts::FontEnvironment;
+use crate::styled_text::StyledText;
+use crate::text_on_path::{line_glyphs, span_paint};
+use skia_safe::path::{Iter, Verb};
+use skia_safe::{Canvas, Matrix, Paint, Path, Point, Rect};
+use std::f32::consts::PI;
+use std::fmt::Write;
+use std::fs;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Warp {
+    // Bends the baseline into a circular arc. A bend of 1 is a half circle, negative bends curve
+    // the other way.
+    Arc { bend: f32 },
+    // Moves glyphs up and down along a sine wave.
+    Wave { amplitude: f32, cycles: f32 },
+    // Stretches the text vertically around its middle, most in the centre. Negative amounts pinch.
+    Bulge { amount: f32 },
+    // A wave whose amplitude grows from nothing at the start, like a flag fixed to a pole.
+    Flag { amplitude: f32, cycles: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WarpMode {
+    // Every outline point is deformed, so glyphs bend with the warp.
+    Outline,
+    // Each glyph is only moved and rotated to follow the warp at its centre. Cheaper, and enough
+    // for arcs with a gentle bend.
+    RigidGlyphs,
+}
+
+pub struct WarpedText {
+    // One path per run of glyphs from the same span, with the span's paint.
+    pub paths: Vec<(Path, Paint)>,
+    pub bounds: Rect,
+    pub clusters_missing: usize,
+}
+
+impl WarpedText {
+    pub fn paint(&self, canvas: &mut Canvas) {
+        for (path, paint) in self.paths.iter() {
+            canvas.draw_path(path, paint);
+        }
+    }
+
+    // The warped text as an SVG document, with a view box around its bounds.
+    pub fn to_svg(&self) -> String {
+        let bounds = self.bounds.round_out();
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
+            bounds.width(),
+            bounds.height(),
+            bounds.left,
+            bounds.top,
+            bounds.width(),
+            bounds.height()
+        )
+        .unwrap();
+        for (path, paint) in self.paths.iter() {
+            let colour = paint.color();
+            writeln!(
+                svg,
+                "  <path fill=\"#{:02x}{:02x}{:02x}\" fill-opacity=\"{:.3}\" d=\"{}\"/>",
+                colour.r(),
+                colour.g(),
+                colour.b(),
+                colour.a() as f32 / 255.0,
+                svg_path_data(path)
+            )
+            .unwrap();
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn write_svg(&self, file_path: &str) -> bool {
+        fs::write(file_path, self.to_svg()).is_ok()
+    }
+}
+
+// Lays `text` out on a single line (see `text_on_path::line_glyphs()`), converts the glyphs to
+// outlines and deforms them. The warp spans the advance of the whole line, with its baseline at
+// y = 0.
+pub fn warp_text(
+    text: &StyledText,
+    fonts: &FontEnvironment,
+    warp: Warp,
+    mode: WarpMode,
+) -> WarpedText {
+    let (glyphs, clusters_missing) = line_glyphs(text, fonts);
+    let line_start = glyphs.first().map(|glyph| glyph.x).unwrap_or(0.0);
+    let width = glyphs
+        .last()
+        .map(|glyph| glyph.x + glyph.advance - line_start)
+        .unwrap_or(0.0);
+    let ascent = glyphs
+        .iter()
+        .map(|glyph| -glyph.font.metrics().1.ascent)
+        .fold(0.0, f32::max);
+    let field = Field {
+        warp,
+        width,
+        ascent,
+    };
+
+    let mut paths: Vec<((usize, usize), Path)> = vec![];
+    for glyph in glyphs.iter() {
+        let outline = match glyph.font.get_path(glyph.id) {
+            Some(outline) => outline.with_offset((glyph.x - line_start, glyph.y_offset)),
+            None => continue,
+        };
+        let warped = match mode {
+            WarpMode::Outline => field.warp_path(&outline),
+            WarpMode::RigidGlyphs => outline
+                .with_transform(&field.rigid_transform(glyph.x - line_start + glyph.advance / 2.0)),
+        };
+        match paths.last_mut() {
+            Some((run, path)) if *run == glyph.run => {
+                path.add_path(&warped, (0.0, 0.0), None);
+            }
+            _ => paths.push((glyph.run, warped)),
+        }
+    }
+
+    let mut bounds = Rect::new_empty();
+    for (_, path) in paths.iter() {
+        bounds.join(path.compute_tight_bounds());
+    }
+    WarpedText {
+        paths: paths
+            .into_iter()
+            .map(|((span, _), path)| (path, span_paint(text, span)))
+            .collect(),
+        bounds,
+        clusters_missing,
+    }
+}
+
+struct Field {
+    warp: Warp,
+    width: f32,
+    ascent: f32,
+}
+
+impl Field {
+    fn map(&self, point: Point) -> Point {
+        let u = if self.width > 0.0 {
+            point.x / self.width
+        } else {
+            0.0
+        };
+        match self.warp {
+            Warp::Arc { bend } => {
+                if bend.abs() < 0.001 {
+                    return point;
+                }
+                let sign = bend.signum();
+                let radius = self.width / (bend.abs() * PI);
+                let angle = (point.x - self.width / 2.0) / radius;
+                let distance = radius - sign * point.y;
+                Point::new(
+                    self.width / 2.0 + distance * angle.sin(),
+                    sign * radius - sign * distance * angle.cos(),
+                )
+            }
+            Warp::Wave { amplitude, cycles } => {
+                Point::new(point.x, point.y + amplitude * (2.0 * PI * cycles * u).sin())
+            }
+            Warp::Bulge { amount } => {
+                let middle = -self.ascent / 2.0;
+                let stretch = 1.0 + amount * (PI * u.max(0.0).min(1.0)).sin();
+                Point::new(point.x, middle + (point.y - middle) * stretch)
+            }
+            Warp::Flag { amplitude, cycles } => Point::new(
+                point.x,
+                point.y + amplitude * u * (2.0 * PI * cycles * u).sin(),
+            ),
+        }
+    }
+
+    // Moves a glyph centred on `x` to where the warp takes its baseline, rotated to the warped
+    // baseline's direction.
+    fn rigid_transform(&self, x: f32) -> Matrix {
+        let centre = self.map(Point::new(x, 0.0));
+        let tangent = self.map(Point::new(x + 0.5, 0.0)) - self.map(Point::new(x - 0.5, 0.0));
+        let mut matrix = Matrix::translate(centre);
+        matrix.pre_rotate(tangent.y.atan2(tangent.x).to_degrees(), None);
+        matrix.pre_translate((-x, 0.0));
+        matrix
+    }
+
+    // Straight lines stay straight under a warp only by chance, so every segment is flattened
+    // into short lines before its points are moved.
+    fn warp_path(&self, path: &Path) -> Path {
+        const STEP: f32 = 2.0;
+        let mut warped = Path::new();
+        let line_to = |warped: &mut Path, from: Point, points: &[Point]| {
+            let length: f32 = points
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).length())
+                .sum::<f32>()
+                + (points[0] - from).length();
+            let steps = ((length / STEP).ceil() as usize).max(1);
+            for step in 1..=steps {
+                let t = step as f32 / steps as f32;
+                warped.line_to(self.map(bezier(from, points, t)));
+            }
+        };
+        for (verb, points) in Iter::new(path, false) {
+            match verb {
+                Verb::Move => {
+                    warped.move_to(self.map(points[0]));
+                }
+                // The first point of a segment is where the previous one ended. Glyph outlines
+                // have no conics, their weight is ignored.
+                Verb::Line | Verb::Quad | Verb::Conic | Verb::Cubic => {
+                    line_to(&mut warped, points[0], &points[1..]);
+                }
+                Verb::Close => {
+                    warped.close();
+                }
+                Verb::Done => break,
+            }
+        }
+        warped
+    }
+}
+
+// A point on the Bézier curve from `from` through the control points, by de Casteljau.
+fn bezier(from: Point, points: &[Point], t: f32) -> Point {
+    let mut points: Vec<Point> = std::iter::once(from)
+        .chain(points.iter().copied())
+        .collect();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| pair[0] + (pair[1] - pair[0]) * t)
+            .collect();
+    }
+    points[0]
+}
+
+// Path data in SVG syntax, with two decimals.
+pub fn svg_path_data(path: &Path) -> String {
+    let mut data = String::new();
+    for (verb, points) in Iter::new(path, false) {
+        let (command, points) = match verb {
+            Verb::Move => ("M", &points[..]),
+            Verb::Line => ("L", &points[1..]),
+            Verb::Quad | Verb::Conic => ("Q", &points[1..]),
+            Verb::Cubic => ("C", &points[1..]),
+            Verb::Close => ("Z", &points[..0]),
+            Verb::Done => break,
+        };
+        data.push_str(command);
+        for point in points {
+            write!(data, "{:.2} {:.2} ", point.x, point.y).unwrap();
+        }
+    }
+    data.trim_end().to_string()
+}
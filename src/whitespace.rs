@@ -0,0 +1,158 @@
+use crate::text_index::utf8_to_utf16;
+use skia_safe::canvas::ClipOp;
+use skia_safe::textlayout::{Paragraph, RectHeightStyle, RectWidthStyle, TextAlign};
+use skia_safe::{Canvas, Point, Rect};
+use std::ops::Range;
+
+// How trailing whitespace counts towards a line's width, for alignment and bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrailingWhitespace {
+    // Only the ink-bearing part of the line counts, as SkParagraph aligns lines.
+    Exclude,
+    // All of the whitespace counts, even past the layout width.
+    Include,
+    // The whitespace counts as far as the layout width, so lines never get wider than it.
+    IncludeUpToLayoutWidth,
+}
+
+pub struct LineWhitespace {
+    pub line_number: usize,
+    // UTF-8 range of the trailing whitespace, without a line break ending the line.
+    pub range: Range<usize>,
+    // Horizontal extent of the line without and with its trailing whitespace.
+    pub content: (f32, f32),
+    pub with_whitespace: (f32, f32),
+    pub whitespace_rects: Vec<Rect>,
+    // Whether the whitespace reaches past the layout width.
+    pub hangs: bool,
+}
+
+impl LineWhitespace {
+    pub fn width_without_whitespace(&self) -> f32 {
+        self.content.1 - self.content.0
+    }
+
+    pub fn width_with_whitespace(&self) -> f32 {
+        self.with_whitespace.1 - self.with_whitespace.0
+    }
+
+    // The horizontal extent of the line counted by `mode`.
+    pub fn extent(&self, mode: TrailingWhitespace, layout_width: f32) -> (f32, f32) {
+        match mode {
+            TrailingWhitespace::Exclude => self.content,
+            TrailingWhitespace::Include => self.with_whitespace,
+            TrailingWhitespace::IncludeUpToLayoutWidth => (
+                self.with_whitespace.0.max(self.content.0.min(0.0)),
+                self.with_whitespace.1.min(self.content.1.max(layout_width)),
+            ),
+        }
+    }
+
+    pub fn width(&self, mode: TrailingWhitespace, layout_width: f32) -> f32 {
+        let (left, right) = self.extent(mode, layout_width);
+        right - left
+    }
+}
+
+// Measures the trailing whitespace of every line of `paragraph`, laid out from `text` at
+// `layout_width`. Rects come from get_rects_for_range() with tight widths, so they follow the
+// visual order: in right-to-left lines the whitespace is on the left.
+pub fn measure_trailing_whitespace(
+    paragraph: &Paragraph,
+    text: &str,
+    layout_width: f32,
+) -> Vec<LineWhitespace> {
+    let rects = |range: Range<usize>| -> Vec<Rect> {
+        if range.is_empty() {
+            return vec![];
+        }
+        paragraph
+            .get_rects_for_range(
+                utf8_to_utf16(text, range.start)..utf8_to_utf16(text, range.end),
+                RectHeightStyle::Max,
+                RectWidthStyle::Tight,
+            )
+            .iter()
+            .map(|text_box| text_box.rect)
+            .collect()
+    };
+    paragraph
+        .get_line_metrics()
+        .iter()
+        .map(|line| {
+            let line_text = &text[line.start_index..line.end_including_newline];
+            let whitespace_end =
+                line.start_index + line_text.trim_end_matches(|c| c == '\n' || c == '\r').len();
+            let whitespace_start = line.end_excluding_whitespaces.min(whitespace_end);
+            let content_rects = rects(line.start_index..whitespace_start);
+            let whitespace_rects = rects(whitespace_start..whitespace_end);
+            // An empty line has no rects; it sits where the alignment puts it.
+            let empty = (line.left as f32, line.left as f32);
+            let content = extent(content_rects.iter()).unwrap_or(empty);
+            let with_whitespace =
+                extent(content_rects.iter().chain(whitespace_rects.iter())).unwrap_or(content);
+            LineWhitespace {
+                line_number: line.line_number,
+                range: whitespace_start..whitespace_end,
+                content,
+                with_whitespace,
+                hangs: with_whitespace.0 < -0.01 || with_whitespace.1 > layout_width + 0.01,
+                whitespace_rects,
+            }
+        })
+        .collect()
+}
+
+fn extent<'a>(rects: impl Iterator<Item = &'a Rect>) -> Option<(f32, f32)> {
+    rects.fold(None, |extent, rect| match extent {
+        None => Some((rect.left, rect.right)),
+        Some((left, right)) => Some((left.min(rect.left), right.max(rect.right))),
+    })
+}
+
+// The width of the widest line counted by `mode`, e.g. for a bounding box.
+pub fn bounding_width(
+    lines: &[LineWhitespace],
+    mode: TrailingWhitespace,
+    layout_width: f32,
+) -> f32 {
+    lines
+        .iter()
+        .map(|line| line.width(mode, layout_width))
+        .fold(0.0, f32::max)
+}
+
+// Paints `paragraph` with every line moved so that its extent counted by `mode` is aligned by
+// `align` within `layout_width`. SkParagraph can only paint all lines at once, so each line is
+// painted clipped to its own band. Justified and start/end alignments are treated as left and
+// right for left-to-right text.
+pub fn paint_aligned(
+    paragraph: &Paragraph,
+    lines: &[LineWhitespace],
+    mode: TrailingWhitespace,
+    align: TextAlign,
+    layout_width: f32,
+    canvas: &mut Canvas,
+    origin: Point,
+) {
+    let metrics = paragraph.get_line_metrics();
+    for (line, metrics) in lines.iter().zip(metrics.iter()) {
+        let (left, right) = line.extent(mode, layout_width);
+        let aligned_left = match align {
+            TextAlign::Left | TextAlign::Start | TextAlign::Justify => 0.0,
+            TextAlign::Right | TextAlign::End => layout_width - (right - left),
+            TextAlign::Center => (layout_width - (right - left)) / 2.0,
+        };
+        let top = (metrics.baseline - metrics.ascent) as f32;
+        let band = Rect::from_xywh(
+            origin.x - 1_000_000_f32,
+            origin.y + top,
+            2_000_000_f32,
+            metrics.height as f32,
+        );
+        canvas.save();
+        canvas.clip_rect(band, ClipOp::Intersect, true);
+        paragraph.paint(canvas, Point::new(origin.x + aligned_left - left, origin.y));
+        canvas.restore();
+    }
+}
@@ -0,0 +1,176 @@
+use crate::fonts::FontEnvironment;
+use crate::render_diff::compare;
+use crate::sfnt::{
+    all_tables, cmap_mappings, flavor_for_tables, table, tag, u16_at, u32_at, write_sfnt,
+    CFF_FLAVOR,
+};
+use crate::styled_text::StyledText;
+use crate::{data_from_file_path, read_rgba_pixels, save_rgba_png};
+use skia_safe::textlayout::{ParagraphStyle, TextStyle};
+use skia_safe::{Color, Data, ISize, Surface, Typeface};
+use std::path::{Path, PathBuf};
+
+const SAMPLE_SIZE: (i32, i32) = (1024, 512);
+
+// Decodes a WOFF2 file into a plain sfnt font.
+//
+// There's no WOFF2 decoder in this crate: skia hands WOFF2 data to FreeType, which reconstructs
+// the sfnt tables (including the transformed glyf/loca) in memory. The tables are read back
+// through the typeface and reassembled, so the output is exactly what skia renders from, bugs
+// in FreeType's decoding included.
+pub fn decode(file_path: &Path) -> Result<Vec<u8>, String> {
+    let data = data_from_file_path(file_path);
+    let bytes = data.as_bytes();
+    if bytes.get(0..4) != Some(&b"wOF2"[..]) {
+        return Err(format!("{} is not a WOFF2 file", file_path.display()));
+    }
+    let flavor = u32_at(bytes, 4).unwrap_or(0);
+    let declared_tables = u16_at(bytes, 12).unwrap_or(0) as usize;
+    let typeface = Typeface::from_data(data, None)
+        .ok_or_else(|| format!("skia failed to load {}", file_path.display()))?;
+    let tables = all_tables(&typeface);
+    if tables.len() != declared_tables {
+        println!(
+            "{}: the WOFF2 header declares {} tables, {} were decoded",
+            file_path.display(),
+            declared_tables,
+            tables.len()
+        );
+    }
+    if tables
+        .iter()
+        .all(|(table_tag, _)| *table_tag != tag(b"head"))
+    {
+        return Err(format!("{}: no head table decoded", file_path.display()));
+    }
+    let flavor = if flavor == CFF_FLAVOR {
+        flavor
+    } else {
+        flavor_for_tables(&tables)
+    };
+    Ok(write_sfnt(flavor, &tables))
+}
+
+// Writes the decoded font next to the WOFF2 file with a .ttf or .otf extension, or to
+// `output_path` if given.
+pub fn convert(file_path: &Path, output_path: Option<&Path>) -> Result<PathBuf, String> {
+    let sfnt = decode(file_path)?;
+    let output_path = match output_path {
+        Some(output_path) => output_path.to_path_buf(),
+        None => {
+            let extension = if u32_at(&sfnt, 0) == Some(CFF_FLAVOR) {
+                "otf"
+            } else {
+                "ttf"
+            };
+            file_path.with_extension(extension)
+        }
+    };
+    std::fs::write(&output_path, &sfnt)
+        .map_err(|error| format!("failed to write {}: {}", output_path.display(), error))?;
+    Ok(output_path)
+}
+
+// Checks that the font `decode()` makes from `woff2_path` renders exactly like the .ttf sibling,
+// so a rendering bug seen with the WOFF2 file can be told apart from one in layout. The decoded
+// bytes are loaded as a font of their own, which catches mistakes in the reassembled table
+// directory, offsets or checksums. The WOFF2 file itself, rendered through FreeType's WOFF2
+// support, is compared with the sibling too. Without a sibling the decoded font is compared
+// with the WOFF2 file only.
+pub fn verify_against_sibling(
+    woff2_path: &Path,
+    sibling_path: Option<&Path>,
+) -> Result<(), String> {
+    let sfnt = decode(woff2_path)?;
+    let name = woff2_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let decoded = Typeface::from_data(Data::new_copy(&sfnt), None)
+        .ok_or_else(|| format!("skia failed to load the font decoded from {}", name))?;
+    let woff2 = Typeface::from_data(data_from_file_path(woff2_path), None)
+        .ok_or_else(|| format!("skia failed to load {}", woff2_path.display()))?;
+    let sibling_path = match sibling_path.filter(|path| path.exists()) {
+        Some(sibling_path) => sibling_path,
+        None => {
+            let sample = sample_text(&woff2);
+            report(
+                &format!("{}: decoded vs woff2 (no sibling)", name),
+                &render_sample(woff2, &sample),
+                &render_sample(decoded, &sample),
+                &format!("output/woff2_{}_decoded_diff.png", name),
+            );
+            return Ok(());
+        }
+    };
+    let sibling = Typeface::from_data(data_from_file_path(sibling_path), None)
+        .ok_or_else(|| format!("skia failed to load {}", sibling_path.display()))?;
+    let sample = sample_text(&sibling);
+    let sibling_pixels = render_sample(sibling, &sample);
+    report(
+        &format!("{}: decoded vs {}", name, sibling_path.display()),
+        &sibling_pixels,
+        &render_sample(decoded, &sample),
+        &format!("output/woff2_{}_decoded_diff.png", name),
+    );
+    report(
+        &format!("{}: woff2 vs {}", name, sibling_path.display()),
+        &sibling_pixels,
+        &render_sample(woff2, &sample),
+        &format!("output/woff2_{}_sibling_diff.png", name),
+    );
+    Ok(())
+}
+
+fn report(label: &str, expected: &[u8], actual: &[u8], diff_path: &str) {
+    let (comparison, diff) = compare(expected, actual);
+    if comparison.identical() {
+        println!("{}: identical", label);
+    } else {
+        println!(
+            "{}: {} pixels differ, max channel difference {}, see {}",
+            label, comparison.differing_pixels, comparison.max_channel_difference, diff_path
+        );
+        save_rgba_png(&diff, SAMPLE_SIZE.0, SAMPLE_SIZE.1, diff_path);
+    }
+}
+
+// A pangram plus a spread of characters from the font's own cmap, so that fonts without Latin
+// coverage are still exercised.
+fn sample_text(typeface: &Typeface) -> String {
+    let mut sample = "The quick brown fox jumps over the lazy dog 0123456789\n".to_string();
+    let mappings = table(typeface, b"cmap")
+        .map(|cmap| cmap_mappings(&cmap))
+        .unwrap_or_default();
+    let step = (mappings.len() / 200).max(1);
+    let characters: String = mappings
+        .iter()
+        .step_by(step)
+        .filter_map(|(code_point, _)| std::char::from_u32(*code_point))
+        .filter(|character| !character.is_control() && !character.is_whitespace())
+        .take(200)
+        .collect();
+    sample.push_str(&characters);
+    sample.push('\n');
+    sample
+}
+
+fn render_sample(typeface: Typeface, sample: &str) -> Vec<u8> {
+    let mut fonts = FontEnvironment::new();
+    fonts.register_typeface(typeface, "Sample");
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(32.0);
+    text_style.set_font_families(&vec!["Sample"]);
+    let mut style = ParagraphStyle::new();
+    style.set_text_style(&text_style);
+    let mut text = StyledText::new(style);
+    text.add_span(sample, text_style);
+    let paragraph = text.layout(&fonts, SAMPLE_SIZE.0 as f32);
+
+    let mut surface =
+        Surface::new_raster_n32_premul(ISize::new(SAMPLE_SIZE.0, SAMPLE_SIZE.1)).unwrap();
+    surface.canvas().clear(Color::from_rgb(255, 255, 255));
+    paragraph.paint(surface.canvas(), skia_safe::Point::new(0.0, 0.0));
+    read_rgba_pixels(&mut surface).unwrap_or_default()
+}